@@ -462,6 +462,9 @@ fn main() {
         .allowlist_function("version")
         .allowlist_function("enable_logging")
         .allowlist_function("disable_logging")
+        .allowlist_function("set_log_capture")
+        .allowlist_function("destroy_log_handle")
+        .allowlist_function("get_log_buffer")
         .allowlist_function("get_smiles")
         .allowlist_function("get_mol")
         .allowlist_function("get_inchikey_for_inchi")
@@ -0,0 +1,229 @@
+//! RMSD between conformers, with or without optimal superposition
+//!
+//! The superposition minimizes the RMSD over the selected atom pairs with the quaternion method, which
+//! gives the same rotation as the Kabsch algorithm but never a reflection. The rotation and translation
+//! are then applied to all atoms of the probe conformer.
+//!
+//! ```no_run
+//! use rdkitcffi::align::AtomSelection;
+//! use rdkitcffi::Molecule;
+//!
+//! let mut pose = Molecule::new("OCCc1ccccc1").unwrap();
+//! let mut reference = Molecule::new("OCCc1ccccc1").unwrap();
+//! pose.set_3d_coords(r#"{"randomSeed": 1}"#);
+//! reference.set_3d_coords(r#"{"randomSeed": 2}"#);
+//! let query = Molecule::get_qmol("c1ccccc1", "").unwrap();
+//! let selection = AtomSelection::from_substruct_match(&pose.get_substruct_match(&query, "")).unwrap();
+//! let alignment = pose.align_to(&reference, &selection).unwrap();
+//! println!("ring rmsd {:.3}", alignment.rmsd);
+//! pose.set_coords(&alignment.coords_f32()).unwrap();
+//! ```
+
+use serde_json::Value;
+
+use crate::geometry::{self, Point};
+use crate::json::JsonMolecule;
+
+/// Atoms compared between the probe and the reference
+#[derive(Clone, Debug, PartialEq)]
+pub enum AtomSelection {
+    /// All atoms, both conformers need the same atom order
+    All,
+    /// Atoms of the probe which are not hydrogens, paired with the same index in the reference
+    HeavyAtoms,
+    /// Pairs of probe and reference atom indices
+    Map(Vec<(usize, usize)>),
+}
+
+impl AtomSelection {
+    /// Matched atoms from the output of `get_substruct_match`, each paired with itself
+    pub fn from_substruct_match(json: &str) -> Option<AtomSelection> {
+        let atoms = match_atoms(json)?;
+        Some(AtomSelection::Map(atoms.iter().map(|&a| (a, a)).collect()))
+    }
+
+    /// Pairs the atoms matched by the same query in two molecules, e.g. to align a common scaffold
+    pub fn from_substruct_matches(
+        probe_match: &str,
+        reference_match: &str,
+    ) -> Option<AtomSelection> {
+        let probe = match_atoms(probe_match)?;
+        let reference = match_atoms(reference_match)?;
+        if probe.len() != reference.len() {
+            return None;
+        }
+        Some(AtomSelection::Map(
+            probe.into_iter().zip(reference).collect(),
+        ))
+    }
+
+    /// Probe and reference atom pairs for a probe molecule
+    pub fn pairs(&self, probe: &JsonMolecule) -> Vec<(usize, usize)> {
+        match self {
+            AtomSelection::All => (0..probe.atoms.len()).map(|i| (i, i)).collect(),
+            AtomSelection::HeavyAtoms => probe
+                .atoms
+                .iter()
+                .enumerate()
+                .filter(|(_, a)| a.z != 1)
+                .map(|(i, _)| (i, i))
+                .collect(),
+            AtomSelection::Map(pairs) => pairs.clone(),
+        }
+    }
+}
+
+/// Atom indices of a substructure match, None for no match or invalid json
+fn match_atoms(json: &str) -> Option<Vec<usize>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let atoms: Option<Vec<usize>> = value
+        .get("atoms")?
+        .as_array()?
+        .iter()
+        .map(|a| a.as_u64().map(|a| a as usize))
+        .collect();
+    atoms.filter(|a| !a.is_empty())
+}
+
+/// Rigid motion superimposing a probe onto a reference
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alignment {
+    /// RMSD of the selected atom pairs after the superposition
+    pub rmsd: f64,
+    /// Rotation matrix applied before the translation
+    pub rotation: [[f64; 3]; 3],
+    pub translation: Point,
+    /// All probe coordinates after the superposition
+    pub coords: Vec<Point>,
+}
+
+impl Alignment {
+    /// Moves a point of the probe frame into the reference frame
+    pub fn transform(&self, p: Point) -> Point {
+        let r = &self.rotation;
+        let mut moved = self.translation;
+        for (k, m) in moved.iter_mut().enumerate() {
+            *m += geometry::dot(r[k], p);
+        }
+        moved
+    }
+
+    /// Transformed coordinates as expected by `Molecule::set_coords`
+    pub fn coords_f32(&self) -> Vec<[f32; 3]> {
+        self.coords
+            .iter()
+            .map(|c| [c[0] as f32, c[1] as f32, c[2] as f32])
+            .collect()
+    }
+}
+
+/// Selected points of probe and reference, None if there are no pairs or an index is out of range
+fn paired_points(
+    probe: &[Point],
+    reference: &[Point],
+    pairs: &[(usize, usize)],
+) -> Option<(Vec<Point>, Vec<Point>)> {
+    if pairs.is_empty() {
+        return None;
+    }
+    pairs
+        .iter()
+        .map(|&(p, r)| Some((*probe.get(p)?, *reference.get(r)?)))
+        .collect::<Option<Vec<(Point, Point)>>>()
+        .map(|p| p.into_iter().unzip())
+}
+
+fn rmsd_of(a: &[Point], b: &[Point]) -> f64 {
+    let sum: f64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| {
+            let d = geometry::sub(*a, *b);
+            geometry::dot(d, d)
+        })
+        .sum();
+    (sum / a.len() as f64).sqrt()
+}
+
+/// RMSD of the atom pairs in place, without superposition
+pub fn rmsd(probe: &[Point], reference: &[Point], pairs: &[(usize, usize)]) -> Option<f64> {
+    let (p, r) = paired_points(probe, reference, pairs)?;
+    Some(rmsd_of(&p, &r))
+}
+
+/// Rotation and translation of the probe minimizing the RMSD of the atom pairs
+pub fn superpose(
+    probe: &[Point],
+    reference: &[Point],
+    pairs: &[(usize, usize)],
+) -> Option<Alignment> {
+    let (p, r) = paired_points(probe, reference, pairs)?;
+    let (cp, cr) = (geometry::centroid(&p)?, geometry::centroid(&r)?);
+    // correlation s[a][b] of the centred probe coordinate a and reference coordinate b
+    let mut s = [[0.0; 3]; 3];
+    for (x, y) in p.iter().zip(r.iter()) {
+        let (x, y) = (geometry::sub(*x, cp), geometry::sub(*y, cr));
+        for a in 0..3 {
+            for b in 0..3 {
+                s[a][b] += x[a] * y[b];
+            }
+        }
+    }
+    let n = [
+        [
+            s[0][0] + s[1][1] + s[2][2],
+            s[1][2] - s[2][1],
+            s[2][0] - s[0][2],
+            s[0][1] - s[1][0],
+        ],
+        [
+            s[1][2] - s[2][1],
+            s[0][0] - s[1][1] - s[2][2],
+            s[0][1] + s[1][0],
+            s[2][0] + s[0][2],
+        ],
+        [
+            s[2][0] - s[0][2],
+            s[0][1] + s[1][0],
+            -s[0][0] + s[1][1] - s[2][2],
+            s[1][2] + s[2][1],
+        ],
+        [
+            s[0][1] - s[1][0],
+            s[2][0] + s[0][2],
+            s[1][2] + s[2][1],
+            -s[0][0] - s[1][1] + s[2][2],
+        ],
+    ];
+    let (_, vectors) = geometry::symmetric_eigen(n);
+    let [q0, q1, q2, q3] = vectors[0];
+    let rotation = [
+        [
+            q0 * q0 + q1 * q1 - q2 * q2 - q3 * q3,
+            2.0 * (q1 * q2 - q0 * q3),
+            2.0 * (q1 * q3 + q0 * q2),
+        ],
+        [
+            2.0 * (q1 * q2 + q0 * q3),
+            q0 * q0 - q1 * q1 + q2 * q2 - q3 * q3,
+            2.0 * (q2 * q3 - q0 * q1),
+        ],
+        [
+            2.0 * (q1 * q3 - q0 * q2),
+            2.0 * (q2 * q3 + q0 * q1),
+            q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3,
+        ],
+    ];
+    let mut alignment = Alignment {
+        rmsd: 0.0,
+        rotation,
+        translation: [0.0; 3],
+        coords: Vec::new(),
+    };
+    let rotated = alignment.transform(cp);
+    alignment.translation = geometry::sub(cr, rotated);
+    let moved: Vec<Point> = p.iter().map(|x| alignment.transform(*x)).collect();
+    alignment.rmsd = rmsd_of(&moved, &r);
+    alignment.coords = probe.iter().map(|x| alignment.transform(*x)).collect();
+    Some(alignment)
+}
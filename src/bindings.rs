@@ -249,6 +249,17 @@ unsafe extern "C" {
 unsafe extern "C" {
     pub fn disable_logging() -> ::std::os::raw::c_short;
 }
+unsafe extern "C" {
+    pub fn set_log_capture(log_name: *const ::std::os::raw::c_char) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn destroy_log_handle(
+        log_handle: *mut *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_short;
+}
+unsafe extern "C" {
+    pub fn get_log_buffer(log_handle: *mut ::std::os::raw::c_void) -> *mut ::std::os::raw::c_char;
+}
 unsafe extern "C" {
     pub fn has_prop(
         mol_pkl: *const ::std::os::raw::c_char,
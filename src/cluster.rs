@@ -0,0 +1,104 @@
+//! Taylor-Butina clustering of fingerprints
+//!
+//! ```no_run
+//! use rdkitcffi::cluster::butina_cluster;
+//! use rdkitcffi::fingerprint::FingerprintKind;
+//!
+//! let mol_list = rdkitcffi::read_smifile_unwrap("data/ringtest.smi");
+//! let fps = FingerprintKind::Morgan.compute_all(&mol_list, r#"{"radius":2,"nBits":2048}"#);
+//! let clustering = butina_cluster(&fps, 0.6, true);
+//! for cluster in clustering.clusters.iter() {
+//!     println!("centroid: {} size: {}", cluster[0], cluster.len());
+//! }
+//! ```
+
+use rayon::prelude::*;
+
+use crate::fingerprint;
+
+/// Result of a clustering, each cluster starts with its centroid
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clustering {
+    pub clusters: Vec<Vec<usize>>,
+    /// Cluster index for each fingerprint
+    pub assignments: Vec<usize>,
+}
+
+impl Clustering {
+    pub fn num_clusters(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Fingerprint index of the centroid of each cluster
+    pub fn centroids(&self) -> Vec<usize> {
+        self.clusters.iter().map(|c| c[0]).collect()
+    }
+}
+
+/// Indices of all fingerprints within the Tanimoto distance threshold, including the fingerprint itself
+fn neighbours(fps: &[Vec<u8>], counts: &[u32], i: usize, min_similarity: f32) -> Vec<usize> {
+    (0..fps.len())
+        .filter(|&j| {
+            j == i
+                || fingerprint::tanimoto_with_counts(&fps[i], &fps[j], counts[i], counts[j])
+                    >= min_similarity
+        })
+        .collect()
+}
+
+/// Computes the neighbour lists for all fingerprints, the distance matrix is never stored
+pub fn neighbour_lists(fps: &[Vec<u8>], threshold: f32, parallel: bool) -> Vec<Vec<usize>> {
+    let counts: Vec<u32> = fps.iter().map(|fp| fingerprint::popcount(fp)).collect();
+    let min_similarity = 1.0 - threshold;
+    if parallel {
+        (0..fps.len())
+            .into_par_iter()
+            .map(|i| neighbours(fps, &counts, i, min_similarity))
+            .collect()
+    } else {
+        let mut lists: Vec<Vec<usize>> = (0..fps.len()).map(|i| vec![i]).collect();
+        for i in 0..fps.len() {
+            for j in i + 1..fps.len() {
+                let sim = fingerprint::tanimoto_with_counts(&fps[i], &fps[j], counts[i], counts[j]);
+                if sim >= min_similarity {
+                    lists[i].push(j);
+                    lists[j].push(i);
+                }
+            }
+        }
+        lists.iter_mut().for_each(|l| l.sort_unstable());
+        lists
+    }
+}
+
+/// Taylor-Butina clustering with a Tanimoto distance threshold (1 - similarity).
+///
+/// Fingerprints with the most neighbours become centroids first, all of their neighbours which are not
+/// yet assigned form the cluster. Setting parallel computes the neighbour lists in a thread pool.
+pub fn butina_cluster(fps: &[Vec<u8>], threshold: f32, parallel: bool) -> Clustering {
+    let lists = neighbour_lists(fps, threshold, parallel);
+    let mut order: Vec<usize> = (0..fps.len()).collect();
+    order.sort_by(|&a, &b| lists[b].len().cmp(&lists[a].len()).then(a.cmp(&b)));
+
+    let mut assignments: Vec<Option<usize>> = vec![None; fps.len()];
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for idx in order {
+        if assignments[idx].is_some() {
+            continue;
+        }
+        let cluster_idx = clusters.len();
+        let mut cluster = vec![idx];
+        assignments[idx] = Some(cluster_idx);
+        for &nbr in lists[idx].iter() {
+            if assignments[nbr].is_none() {
+                assignments[nbr] = Some(cluster_idx);
+                cluster.push(nbr);
+            }
+        }
+        clusters.push(cluster);
+    }
+    Clustering {
+        clusters,
+        assignments: assignments.into_iter().flatten().collect(),
+    }
+}
@@ -0,0 +1,171 @@
+//! Transparent gzip and zstd (de)compression for the file readers and writers
+//!
+//! Readers detect compressed input by its magic bytes, writers compress if the file name ends with `.gz` or
+//! `.zst`. All path based constructors e.g. [`crate::SDIterator::new`] or [`crate::SDWriter::create`] use
+//! these functions.
+//!
+//! ```no_run
+//! use rdkitcffi::SDIterator;
+//!
+//! let n = SDIterator::new("library.sdf.gz").count();
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Guesses the compression from the file extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Compression {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("gz") | Some("gzip") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Detects the compression from the first bytes of a stream
+    pub fn from_magic(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wraps a buffered reader with a decompressor if its content starts with gzip or zstd magic bytes
+pub fn decompress<R: BufRead + Send + 'static>(
+    mut reader: R,
+) -> io::Result<Box<dyn BufRead + Send>> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    })
+}
+
+/// Opens a file for reading, decompressing it if needed
+pub fn open_reader<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn BufRead + Send>> {
+    decompress(BufReader::new(File::open(path)?))
+}
+
+/// Opens a file for reading and returns its whole (decompressed) content
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut content = String::new();
+    open_reader(path)?.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// The encoder of a [`CompressedWriter`]
+enum Encoder {
+    Plain(BufWriter<File>),
+    Gzip(BufWriter<GzEncoder<File>>),
+    Zstd(BufWriter<zstd::Encoder<'static, File>>),
+}
+
+impl Encoder {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Encoder::Plain(w) => w,
+            Encoder::Gzip(w) => w,
+            Encoder::Zstd(w) => w,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?
+                .flush(),
+            Encoder::Gzip(w) => w
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?
+                .finish()?
+                .flush(),
+            Encoder::Zstd(w) => w
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)?
+                .finish()?
+                .flush(),
+        }
+    }
+}
+
+/// A file writer compressing according to the file extension.
+/// `finish` completes the compressed stream and reports all errors, dropping the writer does the same but
+/// ignores them.
+pub struct CompressedWriter {
+    encoder: Option<Encoder>,
+}
+
+impl CompressedWriter {
+    /// Flushes the buffers and writes the trailer of a compressed stream
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.encoder.take() {
+            Some(encoder) => encoder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn writer(&mut self) -> io::Result<&mut dyn Write> {
+        match self.encoder.as_mut() {
+            Some(encoder) => Ok(encoder.writer()),
+            None => Err(io::Error::other("Writer is already finished")),
+        }
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer()?.write(buf)
+    }
+
+    /// Flushes the buffered data, a compressed stream is only complete after `finish`
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer()?.flush()
+    }
+}
+
+impl Drop for CompressedWriter {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.finish();
+        }
+    }
+}
+
+/// Creates a file for writing, the content is compressed according to the file extension.
+/// Call `finish` on the writer to complete the file and to be notified of write errors.
+pub fn create_writer<P: AsRef<Path>>(path: P) -> io::Result<CompressedWriter> {
+    let compression = Compression::from_path(&path);
+    let file = File::create(path)?;
+    let encoder = match compression {
+        Compression::None => Encoder::Plain(BufWriter::new(file)),
+        Compression::Gzip => Encoder::Gzip(BufWriter::new(GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        ))),
+        Compression::Zstd => Encoder::Zstd(BufWriter::new(zstd::Encoder::new(file, 0)?)),
+    };
+    Ok(CompressedWriter {
+        encoder: Some(encoder),
+    })
+}
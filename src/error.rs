@@ -0,0 +1,207 @@
+//! Per-record errors of the file readers and errors of molecule edits
+//!
+//! ```no_run
+//! for result in rdkitcffi::read_sdfile("data/test.sdf") {
+//!     match result {
+//!         Ok(mol) => println!("{}", mol.get_smiles("")),
+//!         Err(e) => eprintln!("{}", e),
+//!     }
+//! }
+//! ```
+
+use std::error::Error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io;
+use std::os::raw::c_void;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use crate::bindings::{destroy_log_handle, free_ptr, get_log_buffer, set_log_capture};
+use crate::Molecule;
+
+/// RDKit logs are global: molecules are parsed under a shared lock, while the log of a failed input is
+/// captured the lock is held exclusively, so no other parse logs into the capture. RDKit calls which do not
+/// parse, e.g. coordinate generation in another thread, may still add their messages.
+static PARSE_LOCK: RwLock<()> = RwLock::new(());
+
+const FALLBACK_MESSAGE: &str = "Could not parse molecule";
+
+/// A record of an input file which could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordError {
+    /// Position of the record in the file, starting with 0
+    pub index: usize,
+    /// Line on which the record starts, starting with 1
+    pub line: usize,
+    /// Title line of an SD record or name of a SMILES record
+    pub title: String,
+    /// The error message logged by RDKit, or the I/O error which ended the input
+    pub message: String,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "record {} (line {}", self.index, self.line)?;
+        if !self.title.is_empty() {
+            write!(f, ", {}", self.title)?;
+        }
+        write!(f, "): {}", self.message)
+    }
+}
+
+impl Error for RecordError {}
+
+/// Reason why the coordinates of a conformer could not be set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoordsError {
+    /// The number of coordinates differs from the number of atoms
+    AtomCount { atoms: usize, coords: usize },
+    /// The conformer index is larger than the number of conformers
+    NoConformer { index: usize, conformers: usize },
+    /// RDKit could not rebuild the molecule from its JSON representation
+    Rebuild,
+}
+
+impl fmt::Display for CoordsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordsError::AtomCount { atoms, coords } => {
+                write!(f, "{} coordinates for {} atoms", coords, atoms)
+            }
+            CoordsError::NoConformer { index, conformers } => write!(
+                f,
+                "no conformer {} in a molecule with {} conformers",
+                index, conformers
+            ),
+            CoordsError::Rebuild => {
+                write!(f, "could not rebuild the molecule with new coordinates")
+            }
+        }
+    }
+}
+
+impl Error for CoordsError {}
+
+/// Shared lock held while RDKit parses a molecule
+pub(crate) fn parse_lock() -> RwLockReadGuard<'static, ()> {
+    PARSE_LOCK.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Parses the input while capturing the RDKit error log, which explains a failure
+fn parse_with_log(input: &str, json_info: &str) -> Result<Molecule, String> {
+    if CString::new(input).is_err() || CString::new(json_info).is_err() {
+        return Err("Input contains a nul byte".to_string());
+    }
+    let _guard = PARSE_LOCK.write().unwrap_or_else(|e| e.into_inner());
+    let log_name = CString::new("rdApp.error").unwrap();
+    let mut handle: *mut c_void = unsafe { set_log_capture(log_name.as_ptr()) };
+    if handle.is_null() {
+        return Molecule::parse_unlocked(input, json_info)
+            .ok_or_else(|| FALLBACK_MESSAGE.to_string());
+    }
+    let mol = Molecule::parse_unlocked(input, json_info);
+    let log = unsafe {
+        let buffer = get_log_buffer(handle);
+        let log = if buffer.is_null() {
+            String::new()
+        } else {
+            let log = CStr::from_ptr(buffer).to_string_lossy().into_owned();
+            free_ptr(buffer);
+            log
+        };
+        destroy_log_handle(&mut handle);
+        log
+    };
+    if let Some(mol) = mol {
+        return Ok(mol);
+    }
+    let message = log
+        .lines()
+        .map(strip_timestamp)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if message.is_empty() {
+        Err(FALLBACK_MESSAGE.to_string())
+    } else {
+        Err(message)
+    }
+}
+
+/// Parses the input of a record, the error message is the RDKit log of the failed parse.
+///
+/// Inputs are parsed under the shared lock, so readers in other threads do not wait for each other. Only a
+/// failed input is parsed a second time, with the log captured.
+pub(crate) fn parse_input(input: &str, json_info: &str) -> Result<Molecule, String> {
+    match Molecule::new_with_args(input, json_info) {
+        Some(mol) => Ok(mol),
+        None => match parse_with_log(input, json_info) {
+            Err(message) => Err(message),
+            Ok(_) => Err(FALLBACK_MESSAGE.to_string()),
+        },
+    }
+}
+
+/// Removes the `[hh:mm:ss] ` prefix of RDKit log lines
+fn strip_timestamp(line: &str) -> &str {
+    let line = line.trim();
+    match line.strip_prefix('[').and_then(|l| l.split_once("] ")) {
+        Some((time, message)) if time.chars().all(|c| c.is_ascii_digit() || c == ':') => {
+            message.trim()
+        }
+        _ => line,
+    }
+}
+
+/// Error for a record which could not be parsed
+pub(crate) fn record_error(index: usize, line: usize, title: &str, message: String) -> RecordError {
+    RecordError {
+        index,
+        line,
+        title: title.to_string(),
+        message,
+    }
+}
+
+/// Parses the molecule of a record into the `mol` and `error` fields of the record
+pub(crate) fn parse_record(
+    input: &str,
+    index: usize,
+    line: usize,
+    title: &str,
+) -> (Option<Molecule>, Option<RecordError>) {
+    match parse_input(input, "") {
+        Ok(mol) => (Some(mol), None),
+        Err(message) => (None, Some(record_error(index, line, title, message))),
+    }
+}
+
+/// Turns the `mol` and `error` fields of a record into a result, a record without either gets a generic error
+pub(crate) fn record_result(
+    mol: Option<Molecule>,
+    error: Option<RecordError>,
+    index: usize,
+    line: usize,
+    title: String,
+) -> Result<Molecule, RecordError> {
+    match (mol, error) {
+        (Some(mol), _) => Ok(mol),
+        (None, Some(error)) => Err(error),
+        (None, None) => Err(RecordError {
+            index,
+            line,
+            title,
+            message: "No molecule".to_string(),
+        }),
+    }
+}
+
+/// Error for input which could not be read, e.g. a corrupt compressed file, it ends the iteration
+pub(crate) fn read_error(index: usize, line: usize, e: &io::Error) -> RecordError {
+    RecordError {
+        index,
+        line,
+        title: String::new(),
+        message: format!("Could not read input: {}", e),
+    }
+}
@@ -33,8 +33,9 @@ mod tests {
     use super::*;
     #[test]
     fn polars1() {
-        let mut mol_opt_list: Vec<Option<Molecule>> = crate::read_sdfile("data/test.sdf");
-        let mut mol_list: Vec<Molecule> = mol_opt_list.into_iter().filter_map(|m| m).collect();
+        let mut mol_res_list: Vec<Result<Molecule, crate::RecordError>> =
+            crate::read_sdfile("data/test.sdf");
+        let mut mol_list: Vec<Molecule> = mol_res_list.into_iter().filter_map(|m| m.ok()).collect();
         mol_list.iter_mut().for_each(|m| m.remove_all_hs());
         let a: Vec<_> = mol_list.iter().map(|m| m.get_smiles("")).collect();
         let b: Vec<_> = mol_list
@@ -0,0 +1,87 @@
+//! Bit vector fingerprints computed via the RDKit cffi interface and some basic similarity functions.
+//!
+//! Fingerprints are stored as packed bytes in the same order as returned by e.g.
+//! [`Molecule::get_morgan_fp_as_bytes`], i.e. bit `i` is bit `i % 8` of byte `i / 8`.
+
+use crate::Molecule;
+
+/// The fingerprint types available via cffi
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FingerprintKind {
+    Morgan,
+    RDKit,
+    Pattern,
+}
+
+impl FingerprintKind {
+    /// Numeric code used in binary file formats
+    pub fn code(&self) -> u32 {
+        match self {
+            FingerprintKind::Morgan => 1,
+            FingerprintKind::RDKit => 2,
+            FingerprintKind::Pattern => 3,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Option<FingerprintKind> {
+        match code {
+            1 => Some(FingerprintKind::Morgan),
+            2 => Some(FingerprintKind::RDKit),
+            3 => Some(FingerprintKind::Pattern),
+            _ => None,
+        }
+    }
+
+    /// Computes the packed fingerprint of a molecule, options are passed as json e.g. `{"radius":2,"nBits":2048}`
+    pub fn compute(&self, mol: &Molecule, json_info: &str) -> Vec<u8> {
+        let fp = match self {
+            FingerprintKind::Morgan => mol.get_morgan_fp_as_bytes(json_info),
+            FingerprintKind::RDKit => mol.get_rdkit_fp_as_bytes(json_info),
+            FingerprintKind::Pattern => mol.get_pattern_fp_as_bytes(json_info),
+        };
+        to_unsigned(fp)
+    }
+
+    /// Computes the fingerprints of a list of molecules
+    pub fn compute_all(&self, mols: &[Molecule], json_info: &str) -> Vec<Vec<u8>> {
+        mols.iter().map(|m| self.compute(m, json_info)).collect()
+    }
+}
+
+/// Converts the signed bytes returned by the cffi fingerprint functions
+pub fn to_unsigned(fp: Vec<i8>) -> Vec<u8> {
+    fp.into_iter().map(|b| b as u8).collect()
+}
+
+/// Number of bits set
+pub fn popcount(fp: &[u8]) -> u32 {
+    fp.iter().map(|b| b.count_ones()).sum()
+}
+
+/// Number of bits set in both fingerprints
+pub fn intersect_popcount(a: &[u8], b: &[u8]) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x & y).count_ones())
+        .sum()
+}
+
+/// Returns true if all bits set in a are also set in b
+pub fn is_subset(a: &[u8], b: &[u8]) -> bool {
+    a.iter().zip(b.iter()).all(|(x, y)| x & y == *x)
+}
+
+/// Tanimoto similarity, two empty fingerprints are considered to be identical
+pub fn tanimoto(a: &[u8], b: &[u8]) -> f32 {
+    tanimoto_with_counts(a, b, popcount(a), popcount(b))
+}
+
+/// Tanimoto similarity with precomputed popcounts
+pub fn tanimoto_with_counts(a: &[u8], b: &[u8], count_a: u32, count_b: u32) -> f32 {
+    let common = intersect_popcount(a, b);
+    let union = count_a + count_b - common;
+    if union == 0 {
+        return 1.0;
+    }
+    common as f32 / union as f32
+}
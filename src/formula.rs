@@ -0,0 +1,160 @@
+//! Molecular formula and masses computed from the atoms of a molecule
+//!
+//! The formula is written in Hill order: carbon, hydrogen and then the other elements alphabetically, or all
+//! elements alphabetically if there is no carbon. Isotope labelled atoms are listed in brackets after the
+//! unlabelled atoms of their element, e.g. `C5[13C]H5[2H]`, and the net charge follows RDKit, e.g. `C2H3O2-`
+//! or `C6H14N2+2`.
+//!
+//! ```no_run
+//! use rdkitcffi::Molecule;
+//!
+//! let mol = Molecule::new("CC(=O)[O-]").unwrap();
+//! let formula = mol.formula();
+//! println!("{} {:.4} {:.3}", formula, formula.monoisotopic_mass(), formula.average_mass());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::json::JsonMolecule;
+use crate::periodic_table;
+
+/// Atom counts by element and isotope with the net charge
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Formula {
+    /// atomic number and mass number, 0 for natural isotope composition
+    counts: BTreeMap<(u8, u16), u32>,
+    pub charge: i32,
+}
+
+impl Formula {
+    /// Counts the atoms and their implicit hydrogens, atoms with invalid atomic numbers are ignored
+    pub fn new(json_mol: &JsonMolecule) -> Formula {
+        let mut formula = Formula::default();
+        for atom in json_mol.atoms.iter() {
+            if (0..=118).contains(&atom.z) {
+                formula.add(atom.z as u8, atom.isotope.max(0) as u16, 1);
+            }
+            if atom.impHs > 0 {
+                formula.add(1, 0, atom.impHs as u32);
+            }
+            formula.charge += atom.chg;
+        }
+        formula
+    }
+
+    /// Adds atoms of an element, mass number 0 stands for the natural isotope composition
+    pub fn add(&mut self, z: u8, mass_number: u16, count: u32) {
+        if count > 0 {
+            *self.counts.entry((z, mass_number)).or_insert(0) += count;
+        }
+    }
+
+    /// Removes atoms of an element, returns false and leaves the formula unchanged if there are not enough
+    pub fn remove(&mut self, z: u8, mass_number: u16, count: u32) -> bool {
+        let key = (z, mass_number);
+        match self.counts.get(&key).copied() {
+            Some(n) if n > count => {
+                self.counts.insert(key, n - count);
+                true
+            }
+            Some(n) if n == count => {
+                self.counts.remove(&key);
+                true
+            }
+            _ => count == 0,
+        }
+    }
+
+    /// Number of atoms of an element including its labelled isotopes
+    pub fn count(&self, z: u8) -> u32 {
+        self.counts
+            .range((z, 0)..=(z, u16::MAX))
+            .map(|(_, &n)| n)
+            .sum()
+    }
+
+    /// Number of atoms labelled as a specific isotope
+    pub fn isotope_count(&self, z: u8, mass_number: u16) -> u32 {
+        self.counts.get(&(z, mass_number)).copied().unwrap_or(0)
+    }
+
+    pub fn num_atoms(&self) -> u32 {
+        self.counts.values().sum()
+    }
+
+    /// Atomic number, mass number (None if not labelled) and count of each entry, by atomic number
+    pub fn iter(&self) -> impl Iterator<Item = (u8, Option<u16>, u32)> + '_ {
+        self.counts
+            .iter()
+            .map(|(&(z, a), &n)| (z, if a == 0 { None } else { Some(a) }, n))
+    }
+
+    /// Entries in the order they are written
+    fn hill_order(&self) -> Vec<(u8, u16, u32)> {
+        let mut entries: Vec<(u8, u16, u32)> =
+            self.counts.iter().map(|(&(z, a), &n)| (z, a, n)).collect();
+        let has_carbon = self.count(6) > 0;
+        entries.sort_by_key(|&(z, a, _)| {
+            let rank = match z {
+                6 if has_carbon => 0,
+                1 if has_carbon => 1,
+                _ => 2,
+            };
+            (rank, periodic_table::symbol(z as i32).unwrap_or(""), a)
+        });
+        entries
+    }
+
+    /// Sum of the most abundant isotope masses, labelled atoms use the mass of their isotope
+    pub fn monoisotopic_mass(&self) -> f64 {
+        self.mass(periodic_table::monoisotopic_mass)
+    }
+
+    /// Sum of the standard atomic weights, labelled atoms use the mass of their isotope
+    pub fn average_mass(&self) -> f64 {
+        self.mass(|_| None)
+    }
+
+    /// Elements without an unlabelled mass from `natural` fall back to their average mass, isotopes missing
+    /// from the periodic table to their mass number
+    fn mass<F: Fn(i32) -> Option<f64>>(&self, natural: F) -> f64 {
+        self.counts
+            .iter()
+            .map(|(&(z, a), &n)| {
+                let z = z as i32;
+                let mass = if a == 0 {
+                    natural(z)
+                        .or_else(|| periodic_table::element(z).map(|e| e.average_mass))
+                        .unwrap_or(0.0)
+                } else {
+                    periodic_table::isotope(z, a).map_or(a as f64, |i| i.mass)
+                };
+                mass * n as f64
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (z, a, n) in self.hill_order() {
+            let symbol = periodic_table::symbol(z as i32).unwrap_or("");
+            if a == 0 {
+                write!(f, "{}", symbol)?;
+            } else {
+                write!(f, "[{}{}]", a, symbol)?;
+            }
+            if n > 1 {
+                write!(f, "{}", n)?;
+            }
+        }
+        match self.charge {
+            0 => Ok(()),
+            1 => write!(f, "+"),
+            -1 => write!(f, "-"),
+            c if c > 0 => write!(f, "+{}", c),
+            c => write!(f, "-{}", -c),
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! A simple binary on-disk format for fingerprint collections, which can be memory mapped for searching
+//! without recomputing or re-parsing the fingerprints.
+//!
+//! Layout (all integers little endian):
+//!
+//! | content                                  | size                          |
+//! |------------------------------------------|-------------------------------|
+//! | magic `RDKFPDB\0`                        | 8                             |
+//! | format version, fingerprint type         | 2 x u32                       |
+//! | bytes per fingerprint, reserved          | 2 x u32                       |
+//! | record count                             | u64                           |
+//! | options json, toolkit version            | u32 length + utf8 each        |
+//! | padding to 8 bytes                       |                               |
+//! | popcount index (first record per count)  | (nbits + 2) x u64             |
+//! | fingerprints sorted by popcount          | count x bytes per fingerprint |
+//! | id offsets                               | (count + 1) x u64             |
+//! | ids                                      | utf8                          |
+//!
+//! ```no_run
+//! use rdkitcffi::fingerprint::FingerprintKind;
+//! use rdkitcffi::fpdb::{FpDb, FpDbWriter};
+//! use rdkitcffi::Molecule;
+//!
+//! let mut writer = FpDbWriter::new(FingerprintKind::Morgan, r#"{"radius":2,"nBits":2048}"#);
+//! writer.add_molecules(&rdkitcffi::read_smifile_unwrap("data/test.smi")).unwrap();
+//! writer.write("library.fpdb").unwrap();
+//!
+//! let db = FpDb::open("library.fpdb").unwrap();
+//! let query = Molecule::new("CCCO").unwrap();
+//! let hits = db.search_molecule(&query, 0.5).unwrap();
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::fingerprint::{self, FingerprintKind};
+use crate::Molecule;
+
+const MAGIC: &[u8; 8] = b"RDKFPDB\0";
+const FORMAT_VERSION: u32 = 1;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn length_mismatch(len: usize, num_bytes: usize) -> io::Error {
+    invalid_data(&format!(
+        "Fingerprint of {} bytes does not match the {} bytes of the database",
+        len, num_bytes
+    ))
+}
+
+/// Collects fingerprints and writes them as a fingerprint database file
+pub struct FpDbWriter {
+    kind: FingerprintKind,
+    options: String,
+    num_bytes: Option<usize>,
+    records: Vec<(String, Vec<u8>)>,
+}
+
+impl FpDbWriter {
+    /// Creates a writer for the given fingerprint type, options are passed as json to RDKit
+    pub fn new(kind: FingerprintKind, json_info: &str) -> FpDbWriter {
+        FpDbWriter {
+            kind,
+            options: json_info.to_string(),
+            num_bytes: None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Computes and adds the fingerprint of a molecule
+    pub fn add_molecule(&mut self, id: &str, mol: &Molecule) -> io::Result<()> {
+        let fp = self.kind.compute(mol, &self.options);
+        self.add_fingerprint(id, fp)
+    }
+
+    /// Adds molecules using their position as id
+    pub fn add_molecules(&mut self, mols: &[Molecule]) -> io::Result<()> {
+        for mol in mols.iter() {
+            let id = self.records.len().to_string();
+            self.add_molecule(&id, mol)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a precomputed fingerprint, an error if its length differs from the previous ones
+    pub fn add_fingerprint(&mut self, id: &str, fp: Vec<u8>) -> io::Result<()> {
+        match self.num_bytes {
+            Some(n) if n != fp.len() => return Err(length_mismatch(fp.len(), n)),
+            Some(_) => {}
+            None => self.num_bytes = Some(fp.len()),
+        }
+        self.records.push((id.to_string(), fp));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Sorts the records by popcount and writes the database file
+    pub fn write<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.write_to(&mut BufWriter::new(file))
+    }
+
+    /// Sorts the records by popcount and writes the database to any writer
+    pub fn write_to<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let num_bytes = self.num_bytes.unwrap_or(0);
+        let num_bits = num_bytes * 8;
+        self.records
+            .sort_by_key(|(_, fp)| fingerprint::popcount(fp));
+
+        let toolkit_version = crate::rdkit_version();
+        let mut header: Vec<u8> = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&self.kind.code().to_le_bytes());
+        header.extend_from_slice(&(num_bytes as u32).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&(self.records.len() as u64).to_le_bytes());
+        for s in [self.options.as_str(), toolkit_version.as_str()].iter() {
+            header.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            header.extend_from_slice(s.as_bytes());
+        }
+        while !header.len().is_multiple_of(8) {
+            header.push(0);
+        }
+        out.write_all(&header)?;
+
+        // popcount index: position of the first record for each popcount
+        let mut counts = vec![0u64; num_bits + 1];
+        for (_, fp) in self.records.iter() {
+            counts[fingerprint::popcount(fp) as usize] += 1;
+        }
+        let mut start = 0u64;
+        for c in counts.iter() {
+            out.write_all(&start.to_le_bytes())?;
+            start += c;
+        }
+        out.write_all(&start.to_le_bytes())?;
+
+        for (_, fp) in self.records.iter() {
+            out.write_all(fp)?;
+        }
+
+        let mut offset = 0u64;
+        out.write_all(&offset.to_le_bytes())?;
+        for (id, _) in self.records.iter() {
+            offset += id.len() as u64;
+            out.write_all(&offset.to_le_bytes())?;
+        }
+        for (id, _) in self.records.iter() {
+            out.write_all(id.as_bytes())?;
+        }
+        out.flush()
+    }
+}
+
+/// A memory mapped fingerprint database
+pub struct FpDb {
+    mmap: Mmap,
+    kind: FingerprintKind,
+    options: String,
+    toolkit_version: String,
+    num_bytes: usize,
+    count: usize,
+    popcount_index: Vec<usize>,
+    records_start: usize,
+    id_offsets_start: usize,
+    ids_start: usize,
+}
+
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid_data("Unexpected end of fingerprint database"))?;
+        let res = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(res)
+    }
+
+    /// A table of n u64 values
+    fn take_table(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let len = n
+            .checked_mul(8)
+            .ok_or_else(|| invalid_data("Unexpected end of fingerprint database"))?;
+        self.take(len)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn read_string(&mut self) -> io::Result<String> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid_data("Invalid UTF-8 in header"))
+    }
+}
+
+fn table_values(table: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    table.chunks_exact(8).map(|bytes| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(bytes);
+        u64::from_le_bytes(buf)
+    })
+}
+
+/// The last value of a table of offsets, None unless the offsets start at 0 and never decrease
+fn last_offset(table: &[u8]) -> Option<u64> {
+    let mut values = table_values(table);
+    let mut last = values.next().filter(|&first| first == 0)?;
+    for value in values {
+        if value < last {
+            return None;
+        }
+        last = value;
+    }
+    Some(last)
+}
+
+impl FpDb {
+    /// Memory maps a fingerprint database file, the tables are checked to be consistent with the file size
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FpDb> {
+        let file = File::open(path)?;
+        // the file must not be modified while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        let mut cursor = HeaderReader {
+            data: &mmap[..],
+            pos: 0,
+        };
+        if cursor.take(8)? != MAGIC {
+            return Err(invalid_data("Not a fingerprint database file"));
+        }
+        if cursor.read_u32()? != FORMAT_VERSION {
+            return Err(invalid_data("Unsupported fingerprint database version"));
+        }
+        let kind = FingerprintKind::from_code(cursor.read_u32()?)
+            .ok_or_else(|| invalid_data("Unknown fingerprint type"))?;
+        let num_bytes = cursor.read_u32()? as usize;
+        cursor.read_u32()?;
+        let count = usize::try_from(cursor.read_u64()?)
+            .map_err(|_| invalid_data("Invalid record count"))?;
+        let options = cursor.read_string()?;
+        let toolkit_version = cursor.read_string()?;
+        cursor.pos = cursor.pos.div_ceil(8) * 8;
+
+        let index_len = num_bytes
+            .checked_mul(8)
+            .and_then(|num_bits| num_bits.checked_add(2))
+            .ok_or_else(|| invalid_data("Invalid fingerprint size"))?;
+        let index = cursor.take_table(index_len)?;
+        if last_offset(index) != Some(count as u64) {
+            return Err(invalid_data("Invalid popcount index"));
+        }
+        let popcount_index: Vec<usize> = table_values(index).map(|v| v as usize).collect();
+        let records_start = cursor.pos;
+        let records_len = count
+            .checked_mul(num_bytes)
+            .ok_or_else(|| invalid_data("Invalid record count"))?;
+        cursor.take(records_len)?;
+        let id_offsets_start = cursor.pos;
+        let id_offsets = cursor.take_table(count.saturating_add(1))?;
+        let ids_start = cursor.pos;
+        match last_offset(id_offsets) {
+            Some(end) if end <= (cursor.data.len() - ids_start) as u64 => {}
+            _ => return Err(invalid_data("Invalid id offsets")),
+        }
+
+        Ok(FpDb {
+            kind,
+            options,
+            toolkit_version,
+            num_bytes,
+            count,
+            popcount_index,
+            records_start,
+            id_offsets_start,
+            ids_start,
+            mmap,
+        })
+    }
+
+    pub fn kind(&self) -> FingerprintKind {
+        self.kind
+    }
+
+    /// The json options used to generate the fingerprints
+    pub fn options(&self) -> &str {
+        &self.options
+    }
+
+    /// The RDKit version used to generate the fingerprints
+    pub fn toolkit_version(&self) -> &str {
+        &self.toolkit_version
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.num_bytes * 8
+    }
+
+    /// Fingerprint of the record at position i (records are sorted by popcount), None if i is out of range
+    pub fn fingerprint(&self, i: usize) -> Option<&[u8]> {
+        if i >= self.count {
+            return None;
+        }
+        Some(self.record(i))
+    }
+
+    /// Id of the record at position i, None if i is out of range or the id is not valid UTF-8
+    pub fn id(&self, i: usize) -> Option<&str> {
+        if i >= self.count {
+            return None;
+        }
+        // the offsets were checked to increase and to end within the file on open
+        let start = self.ids_start + self.id_offset(i);
+        let end = self.ids_start + self.id_offset(i + 1);
+        std::str::from_utf8(&self.mmap[start..end]).ok()
+    }
+
+    fn record(&self, i: usize) -> &[u8] {
+        let start = self.records_start + i * self.num_bytes;
+        &self.mmap[start..start + self.num_bytes]
+    }
+
+    fn id_offset(&self, i: usize) -> usize {
+        let pos = self.id_offsets_start + i * 8;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&self.mmap[pos..pos + 8]);
+        u64::from_le_bytes(buf) as usize
+    }
+
+    /// Positions of all records with a popcount between min and max (inclusive)
+    pub fn popcount_range(&self, min: u32, max: u32) -> Range<usize> {
+        let num_bits = self.num_bits();
+        let min = (min as usize).min(num_bits + 1);
+        let max = (max as usize).min(num_bits);
+        if min > max {
+            return 0..0;
+        }
+        self.popcount_index[min]..self.popcount_index[max + 1]
+    }
+
+    /// Returns (position, similarity) of all records with a Tanimoto similarity >= threshold, most similar
+    /// first. An error if the query length differs from the fingerprints of the database.
+    pub fn similarity_search(&self, query: &[u8], threshold: f32) -> io::Result<Vec<(usize, f32)>> {
+        if query.len() != self.num_bytes {
+            return Err(length_mismatch(query.len(), self.num_bytes));
+        }
+        let query_count = fingerprint::popcount(query);
+        // popcount bounds for the Tanimoto similarity
+        let range = if threshold > 0.0 {
+            let min = (threshold * query_count as f32 - 1e-4).ceil() as u32;
+            let max = (query_count as f32 / threshold + 1e-4).floor() as u32;
+            self.popcount_range(min, max)
+        } else {
+            0..self.count
+        };
+        let mut hits: Vec<(usize, f32)> = Vec::new();
+        for i in range {
+            let fp = self.record(i);
+            let sim = fingerprint::tanimoto_with_counts(
+                query,
+                fp,
+                query_count,
+                fingerprint::popcount(fp),
+            );
+            if sim >= threshold {
+                hits.push((i, sim));
+            }
+        }
+        hits.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        Ok(hits)
+    }
+
+    /// Computes the query fingerprint with the stored settings and searches the database
+    pub fn search_molecule(&self, mol: &Molecule, threshold: f32) -> io::Result<Vec<(usize, f32)>> {
+        let query = self.kind.compute(mol, &self.options);
+        self.similarity_search(&query, threshold)
+    }
+}
@@ -0,0 +1,331 @@
+//! Reading and writing of fingerprints in the [chemfp](https://chemfp.com/) FPS text format.
+//!
+//! An FPS file consists of a header with `#key=value` metadata lines followed by one record per line,
+//! containing the hex encoded fingerprint and the id separated by a tab:
+//!
+//! ```text
+//! #FPS1
+//! #num_bits=2048
+//! #type=RDKit-Morgan/1 radius=2 fpSize=2048
+//! #software=RDKit/2024.09.6
+//! 0000802000...    ethanol
+//! ```
+//!
+//! The bit order of the hex encoding is the same as the one of e.g. [`Molecule::get_morgan_fp_as_bytes`].
+//!
+//! ```no_run
+//! use rdkitcffi::fingerprint::FingerprintKind;
+//! use rdkitcffi::fps::{FpsHeader, FpsReader, FpsWriter};
+//! use rdkitcffi::Molecule;
+//!
+//! let options = r#"{"radius":2,"nBits":2048}"#;
+//! let header = FpsHeader::for_fingerprint(FingerprintKind::Morgan, options);
+//! let mut writer = FpsWriter::create("library.fps", &header).unwrap();
+//! let mol = Molecule::new("CCO").unwrap();
+//! writer.write_molecule("ethanol", &mol).unwrap();
+//! writer.finish().unwrap();
+//!
+//! let reader = FpsReader::open("library.fps").unwrap();
+//! let records: Vec<_> = reader.filter_map(|r| r.ok()).collect();
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::compression::{self, CompressedWriter};
+use crate::fingerprint::FingerprintKind;
+use crate::Molecule;
+
+const DEFAULT_NUM_BITS: u64 = 2048;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The metadata of an FPS file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FpsHeader {
+    pub num_bits: Option<usize>,
+    /// The fingerprint type e.g. `RDKit-Morgan/1 radius=2 fpSize=2048`
+    pub fp_type: Option<String>,
+    pub software: Option<String>,
+    pub sources: Vec<String>,
+    pub date: Option<String>,
+    /// Any other `#key=value` lines
+    pub extra: Vec<(String, String)>,
+}
+
+impl FpsHeader {
+    /// Creates a header describing RDKit fingerprints generated with the given json options
+    pub fn for_fingerprint(kind: FingerprintKind, json_info: &str) -> FpsHeader {
+        let options: Value = serde_json::from_str(json_info).unwrap_or(Value::Null);
+        let get =
+            |key: &str, default: u64| options.get(key).and_then(Value::as_u64).unwrap_or(default);
+        let num_bits = get("nBits", DEFAULT_NUM_BITS);
+        let fp_type = match kind {
+            FingerprintKind::Morgan => {
+                format!(
+                    "RDKit-Morgan/1 radius={} fpSize={}",
+                    get("radius", 2),
+                    num_bits
+                )
+            }
+            FingerprintKind::RDKit => format!(
+                "RDKit-Fingerprint/2 minPath={} maxPath={} fpSize={}",
+                get("minPath", 1),
+                get("maxPath", 7),
+                num_bits
+            ),
+            FingerprintKind::Pattern => format!("RDKit-Pattern/4 fpSize={}", num_bits),
+        };
+        FpsHeader {
+            num_bits: Some(num_bits as usize),
+            fp_type: Some(fp_type),
+            software: Some(format!("RDKit/{}", crate::rdkit_version())),
+            ..Default::default()
+        }
+    }
+
+    /// Translates the type line back to a fingerprint type and json options, if it describes an RDKit fingerprint
+    pub fn fingerprint_kind(&self) -> Option<(FingerprintKind, String)> {
+        let fp_type = self.fp_type.as_ref()?;
+        let mut parts = fp_type.split_whitespace();
+        let name = parts.next()?;
+        let kind = if name.starts_with("RDKit-Morgan/") {
+            FingerprintKind::Morgan
+        } else if name.starts_with("RDKit-Fingerprint/") {
+            FingerprintKind::RDKit
+        } else if name.starts_with("RDKit-Pattern/") {
+            FingerprintKind::Pattern
+        } else {
+            return None;
+        };
+        let mut options = serde_json::Map::new();
+        for param in parts {
+            let (key, value) = match param.split_once('=') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            let key = match key {
+                "fpSize" => "nBits",
+                "radius" | "minPath" | "maxPath" => key,
+                _ => continue,
+            };
+            if let Ok(v) = value.parse::<u64>() {
+                options.insert(key.to_string(), Value::from(v));
+            }
+        }
+        Some((kind, Value::Object(options).to_string()))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> io::Result<()> {
+        match key {
+            "num_bits" => {
+                let n = value
+                    .parse::<usize>()
+                    .map_err(|_| invalid_data(format!("Invalid num_bits: {}", value)))?;
+                self.num_bits = Some(n);
+            }
+            "type" => self.fp_type = Some(value.to_string()),
+            "software" => self.software = Some(value.to_string()),
+            "source" => self.sources.push(value.to_string()),
+            "date" => self.date = Some(value.to_string()),
+            _ => self.extra.push((key.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
+    fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "#FPS1")?;
+        if let Some(n) = self.num_bits {
+            writeln!(out, "#num_bits={}", n)?;
+        }
+        if let Some(t) = &self.fp_type {
+            writeln!(out, "#type={}", t)?;
+        }
+        if let Some(s) = &self.software {
+            writeln!(out, "#software={}", s)?;
+        }
+        for s in self.sources.iter() {
+            writeln!(out, "#source={}", s)?;
+        }
+        if let Some(d) = &self.date {
+            writeln!(out, "#date={}", d)?;
+        }
+        for (k, v) in self.extra.iter() {
+            writeln!(out, "#{}={}", k, v)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single fingerprint with its id
+#[derive(Clone, Debug, PartialEq)]
+pub struct FpsRecord {
+    pub id: String,
+    pub fingerprint: Vec<u8>,
+}
+
+/// Encodes a fingerprint as lower case hex string
+pub fn to_hex(fp: &[u8]) -> String {
+    fp.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string, returns None for invalid input
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Iterates over the records of an FPS file, the header is parsed on construction
+pub struct FpsReader<R: BufRead> {
+    reader: R,
+    header: FpsHeader,
+    pending: Option<String>,
+    line_no: usize,
+}
+
+impl FpsReader<Box<dyn BufRead + Send>> {
+    /// Opens a plain, gzip or zstd compressed FPS file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        FpsReader::new(compression::open_reader(path)?)
+    }
+}
+
+impl<R: BufRead> FpsReader<R> {
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut fps_reader = FpsReader {
+            reader,
+            header: FpsHeader::default(),
+            pending: None,
+            line_no: 0,
+        };
+        while let Some(line) = fps_reader.read_line()? {
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if let Some(meta) = line.strip_prefix('#') {
+                if fps_reader.line_no == 1 && meta.starts_with("FPS") {
+                    continue;
+                }
+                if let Some((key, value)) = meta.split_once('=') {
+                    fps_reader.header.set(key, value)?;
+                }
+            } else {
+                fps_reader.pending = Some(line.to_string());
+                break;
+            }
+        }
+        Ok(fps_reader)
+    }
+
+    pub fn header(&self) -> &FpsHeader {
+        &self.header
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line_no += 1;
+        Ok(Some(line))
+    }
+
+    fn parse_record(&self, line: &str) -> io::Result<FpsRecord> {
+        let mut fields = line.split('\t');
+        let hex = fields.next().unwrap_or("");
+        let id = fields.next().unwrap_or("").to_string();
+        let fingerprint = from_hex(hex).ok_or_else(|| {
+            invalid_data(format!("Invalid hex fingerprint in line {}", self.line_no))
+        })?;
+        if let Some(n) = self.header.num_bits {
+            if fingerprint.len() != n.div_ceil(8) {
+                return Err(invalid_data(format!(
+                    "Fingerprint length does not match num_bits in line {}",
+                    self.line_no
+                )));
+            }
+        }
+        Ok(FpsRecord { id, fingerprint })
+    }
+}
+
+impl<R: BufRead> Iterator for FpsReader<R> {
+    type Item = io::Result<FpsRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => match self.read_line() {
+                    Ok(Some(line)) => line.trim_end_matches(&['\r', '\n'][..]).to_string(),
+                    Ok(None) => return None,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            if !line.is_empty() {
+                return Some(self.parse_record(&line));
+            }
+        }
+    }
+}
+
+/// Writes fingerprints in the FPS format
+pub struct FpsWriter<W: Write> {
+    out: W,
+    header: FpsHeader,
+}
+
+impl FpsWriter<CompressedWriter> {
+    /// Creates an FPS file, compressed if the name ends with `.gz` or `.zst`
+    pub fn create<P: AsRef<Path>>(path: P, header: &FpsHeader) -> io::Result<Self> {
+        FpsWriter::new(compression::create_writer(path)?, header)
+    }
+
+    /// Completes the file, a compressed file is only valid after this succeeded
+    pub fn finish(self) -> io::Result<()> {
+        self.out.finish()
+    }
+}
+
+impl<W: Write> FpsWriter<W> {
+    /// Creates a writer and writes the header
+    pub fn new(mut out: W, header: &FpsHeader) -> io::Result<Self> {
+        header.write_to(&mut out)?;
+        Ok(FpsWriter {
+            out,
+            header: header.clone(),
+        })
+    }
+
+    pub fn write_fingerprint(&mut self, id: &str, fp: &[u8]) -> io::Result<()> {
+        if let Some(n) = self.header.num_bits {
+            if fp.len() != n.div_ceil(8) {
+                return Err(invalid_data(format!(
+                    "Fingerprint of {} bytes does not match num_bits={}",
+                    fp.len(),
+                    n
+                )));
+            }
+        }
+        writeln!(self.out, "{}\t{}", to_hex(fp), id)
+    }
+
+    /// Computes the fingerprint described by the header type and writes it
+    pub fn write_molecule(&mut self, id: &str, mol: &Molecule) -> io::Result<()> {
+        let (kind, options) = self.header.fingerprint_kind().ok_or_else(|| {
+            invalid_data("Header does not describe an RDKit fingerprint type".to_string())
+        })?;
+        let fp = kind.compute(mol, &options);
+        self.write_fingerprint(id, &fp)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,265 @@
+//! Geometry of conformers: distances, angles, dihedrals, centroid, bounding box and principal axes
+//!
+//! Coordinates are converted to `f64` points, 2D conformers get z = 0. Angles are in degrees, dihedrals
+//! follow the IUPAC sign convention and lie in (-180, 180].
+//!
+//! ```no_run
+//! use rdkitcffi::geometry;
+//! use rdkitcffi::Molecule;
+//!
+//! let mut mol = Molecule::new("CCCC").unwrap();
+//! mol.set_3d_coords("");
+//! let json_mol = mol.get_json_molecule();
+//! let points = geometry::points(json_mol.conformer(0).unwrap());
+//! for [a, b, c, d] in geometry::torsions(&json_mol.bonds) {
+//!     let angle = geometry::dihedral(points[a], points[b], points[c], points[d]);
+//!     println!("{}-{}-{}-{} {:.1}", a, b, c, d, angle);
+//! }
+//! ```
+
+use crate::json::{JsonBond, JsonConformer};
+
+pub type Point = [f64; 3];
+
+/// Sweeps of the Jacobi eigenvalue iteration, far more than needed for small matrices
+const MAX_SWEEPS: usize = 64;
+
+/// Principal axes of a point cloud, sorted by decreasing variance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrincipalAxes {
+    /// Mean squared distance from the centroid along each axis
+    pub moments: [f64; 3],
+    /// Unit vectors, forming a right handed system
+    pub axes: [Point; 3],
+}
+
+pub(crate) fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: Point, b: Point) -> Point {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: Point) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Coordinates of a conformer as points
+pub fn points(conformer: &JsonConformer) -> Vec<Point> {
+    conformer
+        .coords
+        .iter()
+        .map(|c| {
+            let at = |i: usize| c.get(i).copied().unwrap_or(0.0) as f64;
+            [at(0), at(1), at(2)]
+        })
+        .collect()
+}
+
+pub fn distance(a: Point, b: Point) -> f64 {
+    norm(sub(a, b))
+}
+
+/// Angle a-b-c at b
+pub fn angle(a: Point, b: Point, c: Point) -> f64 {
+    let (u, v) = (sub(a, b), sub(c, b));
+    let cos = dot(u, v) / (norm(u) * norm(v));
+    cos.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// Dihedral a-b-c-d, positive if a has to be rotated clockwise to eclipse d when looking from b to c
+pub fn dihedral(a: Point, b: Point, c: Point, d: Point) -> f64 {
+    let (b1, b2, b3) = (sub(b, a), sub(c, b), sub(d, c));
+    let n1 = cross(b1, b2);
+    let n2 = cross(b2, b3);
+    let y = norm(b2) * dot(b1, n2);
+    let x = dot(n1, n2);
+    let angle = y.atan2(x).to_degrees();
+    if angle <= -180.0 {
+        angle + 360.0
+    } else {
+        angle
+    }
+}
+
+/// Mean position, None for an empty conformer
+pub fn centroid(points: &[Point]) -> Option<Point> {
+    if points.is_empty() {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mut sum = [0.0; 3];
+    for p in points.iter() {
+        for k in 0..3 {
+            sum[k] += p[k];
+        }
+    }
+    Some([sum[0] / n, sum[1] / n, sum[2] / n])
+}
+
+/// Minimum and maximum corner of the axis aligned box around the points
+pub fn bounding_box(points: &[Point]) -> Option<(Point, Point)> {
+    let first = *points.first()?;
+    let (mut min, mut max) = (first, first);
+    for p in points.iter() {
+        for k in 0..3 {
+            min[k] = min[k].min(p[k]);
+            max[k] = max[k].max(p[k]);
+        }
+    }
+    Some((min, max))
+}
+
+/// Eigenvalues in decreasing order and the matching unit eigenvectors of a symmetric matrix
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn symmetric_eigen<const N: usize>(mut a: [[f64; N]; N]) -> ([f64; N], [[f64; N]; N]) {
+    let mut v = [[0.0; N]; N];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    let total: f64 = a.iter().flatten().map(|x| x * x).sum();
+    for _ in 0..MAX_SWEEPS {
+        let mut off = 0.0;
+        for p in 0..N {
+            for q in p + 1..N {
+                off += a[p][q] * a[p][q];
+            }
+        }
+        if off <= f64::EPSILON * f64::EPSILON * total {
+            break;
+        }
+        for p in 0..N {
+            for q in p + 1..N {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..N {
+                    let (kp, kq) = (a[k][p], a[k][q]);
+                    a[k][p] = c * kp - s * kq;
+                    a[k][q] = s * kp + c * kq;
+                }
+                for k in 0..N {
+                    let (pk, qk) = (a[p][k], a[q][k]);
+                    a[p][k] = c * pk - s * qk;
+                    a[q][k] = s * pk + c * qk;
+                }
+                for row in v.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+            }
+        }
+    }
+    let mut order: [usize; N] = [0; N];
+    for (i, o) in order.iter_mut().enumerate() {
+        *o = i;
+    }
+    order.sort_by(|&i, &j| a[j][j].total_cmp(&a[i][i]));
+    let mut values = [0.0; N];
+    let mut vectors = [[0.0; N]; N];
+    for (n, &i) in order.iter().enumerate() {
+        values[n] = a[i][i];
+        for k in 0..N {
+            vectors[n][k] = v[k][i];
+        }
+    }
+    (values, vectors)
+}
+
+/// Axes of the covariance of the points around their centroid, None without points or with a coordinate
+/// which is not finite
+pub fn principal_axes(points: &[Point]) -> Option<PrincipalAxes> {
+    if points.iter().flatten().any(|c| !c.is_finite()) {
+        return None;
+    }
+    let center = centroid(points)?;
+    let mut covariance = [[0.0; 3]; 3];
+    for p in points.iter() {
+        let d = sub(*p, center);
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j] / points.len() as f64;
+            }
+        }
+    }
+    let (moments, axes) = symmetric_eigen(covariance);
+    Some(PrincipalAxes {
+        moments,
+        axes: [axes[0], axes[1], cross(axes[0], axes[1])],
+    })
+}
+
+/// Euclidean distances between all points
+pub fn distance_matrix(points: &[Point]) -> Vec<Vec<f64>> {
+    points
+        .iter()
+        .map(|a| points.iter().map(|b| distance(*a, *b)).collect())
+        .collect()
+}
+
+/// Sorted neighbour lists from the bonds
+fn neighbours(bonds: &[JsonBond]) -> Vec<Vec<usize>> {
+    let mut neighbours: Vec<Vec<usize>> = Vec::new();
+    for bond in bonds.iter() {
+        if let [a, b] = bond.atoms[..] {
+            if a < 0 || b < 0 || a == b {
+                continue;
+            }
+            let (a, b) = (a as usize, b as usize);
+            if neighbours.len() <= a.max(b) {
+                neighbours.resize(a.max(b) + 1, Vec::new());
+            }
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+    }
+    neighbours.iter_mut().for_each(|n| n.sort_unstable());
+    neighbours
+}
+
+/// All angles i-j-k between bonded atoms, with i < k
+pub fn bonded_angles(bonds: &[JsonBond]) -> Vec<[usize; 3]> {
+    let mut angles: Vec<[usize; 3]> = Vec::new();
+    for (j, n) in neighbours(bonds).iter().enumerate() {
+        for (x, &i) in n.iter().enumerate() {
+            for &k in n[x + 1..].iter() {
+                angles.push([i, j, k]);
+            }
+        }
+    }
+    angles
+}
+
+/// All torsions i-j-k-l around the bonds j-k, in bond order
+pub fn torsions(bonds: &[JsonBond]) -> Vec<[usize; 4]> {
+    let neighbours = neighbours(bonds);
+    let mut torsions: Vec<[usize; 4]> = Vec::new();
+    for bond in bonds.iter() {
+        if let [j, k] = bond.atoms[..] {
+            if j < 0 || k < 0 || j == k {
+                continue;
+            }
+            let (j, k) = (j as usize, k as usize);
+            for &i in neighbours[j].iter().filter(|&&i| i != k) {
+                for &l in neighbours[k].iter().filter(|&&l| l != j && l != i) {
+                    torsions.push([i, j, k, l]);
+                }
+            }
+        }
+    }
+    torsions
+}
@@ -0,0 +1,170 @@
+//! Graph view of a molecule: neighbours, degrees, hydrogen counts, rings and topological distances
+//!
+//! ```no_run
+//! use rdkitcffi::Molecule;
+//!
+//! let mol = Molecule::new("OCc1ccccc1").unwrap();
+//! let graph = mol.graph();
+//! let path = graph.shortest_path(0, 5).unwrap();
+//! let distances = graph.distance_matrix();
+//! println!("{:?} {:?} {}", path, distances[0], graph.total_hs(0));
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::json::JsonMolecule;
+
+/// Adjacency of a molecule built from the atoms and bonds of its json representation
+#[derive(Clone, Debug)]
+pub struct MolGraph {
+    atomic_numbers: Vec<i32>,
+    implicit_hs: Vec<u32>,
+    /// sorted neighbour atoms with the connecting bond
+    adjacency: Vec<Vec<(usize, usize)>>,
+    rings: Vec<Vec<usize>>,
+}
+
+impl MolGraph {
+    /// Builds the graph, rings are taken from the rdkit extension if there is one
+    pub fn new(json_mol: &JsonMolecule) -> MolGraph {
+        let n = json_mol.atoms.len();
+        let mut adjacency: Vec<Vec<(usize, usize)>> = vec![Vec::new(); n];
+        for (i, bond) in json_mol.bonds.iter().enumerate() {
+            if let [a, b] = bond.atoms[..] {
+                let (a, b) = (a as usize, b as usize);
+                if a < n && b < n {
+                    adjacency[a].push((b, i));
+                    adjacency[b].push((a, i));
+                }
+            }
+        }
+        adjacency.iter_mut().for_each(|a| a.sort_unstable());
+        MolGraph {
+            atomic_numbers: json_mol.atoms.iter().map(|a| a.z).collect(),
+            implicit_hs: json_mol
+                .atoms
+                .iter()
+                .map(|a| a.impHs.max(0) as u32)
+                .collect(),
+            adjacency,
+            rings: json_mol
+                .rdkit_representation()
+                .map(|e| e.atom_rings)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn num_atoms(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    /// Neighbouring atoms in ascending order
+    pub fn neighbours(&self, atom: usize) -> Vec<usize> {
+        self.adjacency[atom].iter().map(|&(n, _)| n).collect()
+    }
+
+    /// Neighbour lists of all atoms
+    pub fn adjacency_list(&self) -> Vec<Vec<usize>> {
+        (0..self.num_atoms()).map(|i| self.neighbours(i)).collect()
+    }
+
+    /// Index of the bond connecting two atoms
+    pub fn bond_between(&self, a: usize, b: usize) -> Option<usize> {
+        self.adjacency[a]
+            .iter()
+            .find(|&&(n, _)| n == b)
+            .map(|&(_, bond)| bond)
+    }
+
+    /// Number of explicit neighbours, including explicit hydrogens
+    pub fn degree(&self, atom: usize) -> usize {
+        self.adjacency[atom].len()
+    }
+
+    /// Number of neighbours which are not hydrogens
+    pub fn heavy_degree(&self, atom: usize) -> usize {
+        self.adjacency[atom]
+            .iter()
+            .filter(|&&(n, _)| self.atomic_numbers[n] != 1)
+            .count()
+    }
+
+    /// Implicit hydrogens plus explicit hydrogen neighbours
+    pub fn total_hs(&self, atom: usize) -> u32 {
+        let explicit = self.adjacency[atom]
+            .iter()
+            .filter(|&&(n, _)| self.atomic_numbers[n] == 1)
+            .count() as u32;
+        self.implicit_hs[atom] + explicit
+    }
+
+    /// Smallest set of smallest rings as perceived by RDKit
+    pub fn rings(&self) -> &[Vec<usize>] {
+        &self.rings
+    }
+
+    pub fn is_in_ring(&self, atom: usize) -> bool {
+        self.rings.iter().any(|r| r.contains(&atom))
+    }
+
+    /// Number of rings an atom is part of, e.g. 2 for fusion atoms
+    pub fn ring_count(&self, atom: usize) -> usize {
+        self.rings.iter().filter(|r| r.contains(&atom)).count()
+    }
+
+    /// Size of the smallest ring containing the atom
+    pub fn smallest_ring_size(&self, atom: usize) -> Option<usize> {
+        self.rings
+            .iter()
+            .filter(|r| r.contains(&atom))
+            .map(|r| r.len())
+            .min()
+    }
+
+    /// Breadth first search, returns the distance and the predecessor of each atom
+    fn bfs(&self, start: usize) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let n = self.num_atoms();
+        let mut distances: Vec<Option<usize>> = vec![None; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        distances[start] = Some(0);
+        queue.push_back(start);
+        while let Some(atom) = queue.pop_front() {
+            let d = distances[atom].unwrap();
+            for &(n, _) in self.adjacency[atom].iter() {
+                if distances[n].is_none() {
+                    distances[n] = Some(d + 1);
+                    previous[n] = Some(atom);
+                    queue.push_back(n);
+                }
+            }
+        }
+        (distances, previous)
+    }
+
+    /// Number of bonds between the atom and all other atoms, None for atoms of other fragments
+    pub fn distances_from(&self, atom: usize) -> Vec<Option<usize>> {
+        self.bfs(atom).0
+    }
+
+    /// Atoms of a shortest path including both ends, ties are resolved towards lower atom indices
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let (distances, previous) = self.bfs(from);
+        distances[to]?;
+        let mut path = vec![to];
+        let mut atom = to;
+        while let Some(p) = previous[atom] {
+            path.push(p);
+            atom = p;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Topological distances between all atoms, None for atoms in different fragments
+    pub fn distance_matrix(&self) -> Vec<Vec<Option<usize>>> {
+        (0..self.num_atoms())
+            .map(|i| self.distances_from(i))
+            .collect()
+    }
+}
@@ -0,0 +1,464 @@
+//! Theoretical isotope patterns and adduct m/z values for mass spectrometry
+//!
+//! The fine structure of a formula is computed by convolving the natural isotope distributions of its
+//! elements, peaks below a fraction of the most intense peak are pruned on the way. Centroiding merges
+//! peaks which can not be resolved at a given resolution (m/Δm, FWHM). Adducts are written in the usual
+//! notation, e.g. `[M+H]+`, `[M+Na]+`, `[2M+H]+`, `[M-H2O+H]+`, `[M+2H]2+` or `[M-H]-`.
+//!
+//! ```no_run
+//! use rdkitcffi::isotope_pattern::{Adduct, IsotopePattern, IsotopeTable};
+//! use rdkitcffi::Molecule;
+//!
+//! let formula = Molecule::new("Cn1cnc2c1c(=O)n(C)c(=O)n2C").unwrap().formula();
+//! let protonated = Adduct::parse("[M+H]+").unwrap();
+//! println!("{:.4}", protonated.mz(&formula).unwrap());
+//! for peak in IsotopePattern::new(&formula).centroid(20000.0).unwrap().peaks() {
+//!     println!("{:.4} {:.4}", peak.mass, peak.probability);
+//! }
+//!
+//! let table = IsotopeTable::new(&formula, &Adduct::common(), Some(20000.0)).unwrap();
+//! println!("{}", table.to_dataframe().unwrap());
+//! ```
+
+use std::fmt;
+
+use polars::df;
+use polars::prelude::{DataFrame, PolarsResult};
+
+use crate::formula::Formula;
+use crate::periodic_table;
+
+pub const ELECTRON_MASS: f64 = 0.000548579909;
+
+/// Default pruning threshold relative to the most intense peak
+pub const DEFAULT_THRESHOLD: f64 = 1e-6;
+
+/// Peaks closer than this are the same isotopic composition
+const MERGE_TOLERANCE: f64 = 1e-6;
+
+/// Peaks of the table below this intensity (% of the most intense peak) are left out
+const MIN_TABLE_INTENSITY: f64 = 0.01;
+
+/// Adducts listed by [`Adduct::common`]
+const COMMON_ADDUCTS: [&str; 10] = [
+    "[M+H]+",
+    "[M+Na]+",
+    "[M+K]+",
+    "[M+NH4]+",
+    "[M+2H]2+",
+    "[M+3H]3+",
+    "[M-H]-",
+    "[M+Cl]-",
+    "[M+HCOO]-",
+    "[M-2H]2-",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peak {
+    /// Mass of the neutral formula or m/z of an ion
+    pub mass: f64,
+    pub probability: f64,
+}
+
+/// Peaks sorted by mass
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsotopePattern {
+    peaks: Vec<Peak>,
+}
+
+/// Drops peaks below the threshold and merges peaks of the same mass
+fn prune(mut peaks: Vec<Peak>, threshold: f64) -> Vec<Peak> {
+    let max = peaks.iter().map(|p| p.probability).fold(0.0, f64::max);
+    peaks.retain(|p| p.probability >= max * threshold);
+    merge(peaks, |_| MERGE_TOLERANCE)
+}
+
+/// Combines neighbouring peaks within the tolerance into their weighted mean
+fn merge<F: Fn(f64) -> f64>(mut peaks: Vec<Peak>, tolerance: F) -> Vec<Peak> {
+    peaks.sort_by(|a, b| a.mass.total_cmp(&b.mass));
+    let mut merged: Vec<Peak> = Vec::with_capacity(peaks.len());
+    for peak in peaks {
+        match merged.last_mut() {
+            Some(last) if peak.mass - last.mass <= tolerance(last.mass) => {
+                let probability = last.probability + peak.probability;
+                if probability > 0.0 {
+                    last.mass =
+                        (last.mass * last.probability + peak.mass * peak.probability) / probability;
+                }
+                last.probability = probability;
+            }
+            _ => merged.push(peak),
+        }
+    }
+    merged
+}
+
+fn convolve(a: &[Peak], b: &[Peak], threshold: f64) -> Vec<Peak> {
+    let mut peaks: Vec<Peak> = Vec::with_capacity(a.len() * b.len());
+    for pa in a.iter() {
+        for pb in b.iter() {
+            peaks.push(Peak {
+                mass: pa.mass + pb.mass,
+                probability: pa.probability * pb.probability,
+            });
+        }
+    }
+    prune(peaks, threshold)
+}
+
+/// Distribution of n atoms by repeated squaring
+fn power(peaks: &[Peak], mut n: u32, threshold: f64) -> Vec<Peak> {
+    let mut result = vec![Peak {
+        mass: 0.0,
+        probability: 1.0,
+    }];
+    let mut base = peaks.to_vec();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = convolve(&result, &base, threshold);
+        }
+        n >>= 1;
+        if n > 0 {
+            base = convolve(&base, &base, threshold);
+        }
+    }
+    result
+}
+
+/// Isotope distribution of a single atom, labelled atoms and elements without stable isotopes have one peak
+fn atom_distribution(z: u8, mass_number: Option<u16>) -> Vec<Peak> {
+    let z = z as i32;
+    let single = |mass: f64| {
+        vec![Peak {
+            mass,
+            probability: 1.0,
+        }]
+    };
+    match mass_number {
+        Some(a) => single(periodic_table::isotope(z, a).map_or(a as f64, |i| i.mass)),
+        None => {
+            let natural: Vec<Peak> = periodic_table::isotopes(z)
+                .iter()
+                .filter(|i| i.abundance > 0.0)
+                .map(|i| Peak {
+                    mass: i.mass,
+                    probability: i.abundance,
+                })
+                .collect();
+            if natural.is_empty() {
+                single(periodic_table::element(z).map_or(0.0, |e| e.average_mass))
+            } else {
+                natural
+            }
+        }
+    }
+}
+
+impl IsotopePattern {
+    /// Fine structure of a formula, pruned at the default threshold
+    pub fn new(formula: &Formula) -> IsotopePattern {
+        IsotopePattern::with_threshold(formula, DEFAULT_THRESHOLD)
+    }
+
+    /// Fine structure, peaks below threshold times the most intense peak are dropped
+    pub fn with_threshold(formula: &Formula, threshold: f64) -> IsotopePattern {
+        let mut peaks = vec![Peak {
+            mass: 0.0,
+            probability: 1.0,
+        }];
+        for (z, mass_number, n) in formula.iter() {
+            let element = power(&atom_distribution(z, mass_number), n, threshold);
+            peaks = convolve(&peaks, &element, threshold);
+        }
+        IsotopePattern { peaks }
+    }
+
+    pub fn peaks(&self) -> &[Peak] {
+        &self.peaks
+    }
+
+    /// Merges peaks which are not resolved at the given resolution (m/Δm at half height), None unless the
+    /// resolution is positive and finite
+    pub fn centroid(&self, resolution: f64) -> Option<IsotopePattern> {
+        if !(resolution > 0.0 && resolution.is_finite()) {
+            return None;
+        }
+        Some(IsotopePattern {
+            peaks: merge(self.peaks.clone(), |mass| mass / resolution),
+        })
+    }
+
+    pub fn most_abundant(&self) -> Option<Peak> {
+        self.peaks
+            .iter()
+            .copied()
+            .max_by(|a, b| a.probability.total_cmp(&b.probability))
+    }
+
+    /// Intensities in % of the most intense peak
+    pub fn relative_intensities(&self) -> Vec<f64> {
+        let max = self.most_abundant().map_or(1.0, |p| p.probability);
+        self.peaks
+            .iter()
+            .map(|p| 100.0 * p.probability / max)
+            .collect()
+    }
+
+    /// Converts the masses of an ion formula to m/z
+    fn into_mz(mut self, charge: i32) -> IsotopePattern {
+        let z = charge.abs().max(1) as f64;
+        for peak in self.peaks.iter_mut() {
+            peak.mass = (peak.mass - charge as f64 * ELECTRON_MASS) / z;
+        }
+        self
+    }
+}
+
+/// An ion formed from multimer molecules M by adding and removing atoms, the charge of the ion is set by the
+/// adduct and not by the formal charges of M
+#[derive(Clone, Debug, PartialEq)]
+pub struct Adduct {
+    pub name: String,
+    pub multimer: u32,
+    pub charge: i32,
+    added: Formula,
+    removed: Formula,
+}
+
+/// Parses element symbols with counts, e.g. `NH4` or `CH3COO`
+fn parse_group(group: &str) -> Option<Formula> {
+    let mut formula = Formula::default();
+    let chars: Vec<char> = group.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_uppercase() {
+            return None;
+        }
+        let mut symbol = chars[i].to_string();
+        i += 1;
+        if i < chars.len() && chars[i].is_ascii_lowercase() {
+            symbol.push(chars[i]);
+            i += 1;
+        }
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        let digits: String = chars[start..i].iter().collect();
+        let count = if digits.is_empty() {
+            1
+        } else {
+            digits.parse().ok()?
+        };
+        formula.add(periodic_table::from_symbol(&symbol)?.z, 0, count);
+    }
+    Some(formula)
+}
+
+/// Splits off leading digits, e.g. `2H` gives 2 and `H`
+fn leading_count(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return Some((1, s));
+    }
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+impl Adduct {
+    /// Parses adducts like `[M+H]+`, `[2M+Na]+`, `[M-H2O+H]+` or `[M+2H]2+`, `M` without charge is the
+    /// neutral molecule
+    pub fn parse(name: &str) -> Option<Adduct> {
+        let name = name.trim();
+        let (body, charge) = match name.strip_prefix('[') {
+            Some(rest) => {
+                let end = rest.find(']')?;
+                (&rest[..end], &rest[end + 1..])
+            }
+            None => (name, ""),
+        };
+        let charge = match charge.chars().last() {
+            None => 0,
+            Some(sign) => {
+                let digits = charge.strip_suffix(sign)?;
+                let n: i32 = if digits.is_empty() {
+                    1
+                } else {
+                    digits.parse().ok()?
+                };
+                match sign {
+                    '+' => n,
+                    '-' => -n,
+                    _ => return None,
+                }
+            }
+        };
+        let (multimer, rest) = leading_count(body)?;
+        let mut rest = rest.strip_prefix('M')?;
+        let mut added = Formula::default();
+        let mut removed = Formula::default();
+        while !rest.is_empty() {
+            let sign = rest.chars().next()?;
+            let term = &rest[sign.len_utf8()..];
+            let end = term.find(['+', '-']).unwrap_or(term.len());
+            let (count, group) = leading_count(&term[..end])?;
+            let target = match sign {
+                '+' => &mut added,
+                '-' => &mut removed,
+                _ => return None,
+            };
+            for (z, _, n) in parse_group(group)?.iter() {
+                target.add(z, 0, n * count);
+            }
+            rest = &term[end..];
+        }
+        if multimer == 0 {
+            return None;
+        }
+        Some(Adduct {
+            name: name.to_string(),
+            multimer,
+            charge,
+            added,
+            removed,
+        })
+    }
+
+    /// `[M+nH]n+`
+    pub fn protonated(n: u32) -> Adduct {
+        match n {
+            1 => Adduct::parse("[M+H]+"),
+            n => Adduct::parse(&format!("[M+{}H]{}+", n, n)),
+        }
+        .unwrap()
+    }
+
+    /// `[M-nH]n-`
+    pub fn deprotonated(n: u32) -> Adduct {
+        match n {
+            1 => Adduct::parse("[M-H]-"),
+            n => Adduct::parse(&format!("[M-{}H]{}-", n, n)),
+        }
+        .unwrap()
+    }
+
+    /// Common ESI adducts of both polarities
+    pub fn common() -> Vec<Adduct> {
+        COMMON_ADDUCTS
+            .iter()
+            .map(|name| Adduct::parse(name).unwrap())
+            .collect()
+    }
+
+    /// The atoms of the ion, None if the formula lacks the atoms to be removed
+    pub fn ion_formula(&self, formula: &Formula) -> Option<Formula> {
+        let mut ion = Formula::default();
+        for (z, mass_number, n) in formula.iter() {
+            ion.add(z, mass_number.unwrap_or(0), n * self.multimer);
+        }
+        for (z, mass_number, n) in self.added.iter() {
+            ion.add(z, mass_number.unwrap_or(0), n);
+        }
+        for (z, mass_number, n) in self.removed.iter() {
+            if !ion.remove(z, mass_number.unwrap_or(0), n) {
+                return None;
+            }
+        }
+        ion.charge = self.charge;
+        Some(ion)
+    }
+
+    /// Monoisotopic m/z, the mass for neutral adducts
+    pub fn mz(&self, formula: &Formula) -> Option<f64> {
+        let ion = self.ion_formula(formula)?;
+        let z = self.charge.abs().max(1) as f64;
+        Some((ion.monoisotopic_mass() - self.charge as f64 * ELECTRON_MASS) / z)
+    }
+
+    /// Fine isotope pattern of the ion in m/z
+    pub fn pattern(&self, formula: &Formula) -> Option<IsotopePattern> {
+        self.pattern_with_threshold(formula, DEFAULT_THRESHOLD)
+    }
+
+    pub fn pattern_with_threshold(
+        &self,
+        formula: &Formula,
+        threshold: f64,
+    ) -> Option<IsotopePattern> {
+        let ion = self.ion_formula(formula)?;
+        Some(IsotopePattern::with_threshold(&ion, threshold).into_mz(self.charge))
+    }
+}
+
+impl fmt::Display for Adduct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Isotope peaks of several adducts as columns, one row per peak
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IsotopeTable {
+    pub adduct: Vec<String>,
+    pub charge: Vec<i32>,
+    /// Nominal mass difference to the monoisotopic peak
+    pub offset: Vec<i32>,
+    pub mz: Vec<f64>,
+    pub probability: Vec<f64>,
+    /// % of the most intense peak of the adduct
+    pub intensity: Vec<f64>,
+}
+
+impl IsotopeTable {
+    /// Peaks of each adduct, centroided if a resolution is given. Adducts which can not be formed, e.g.
+    /// `[M-H]-` without hydrogens, are left out. None for a resolution which is not positive and finite
+    pub fn new(
+        formula: &Formula,
+        adducts: &[Adduct],
+        resolution: Option<f64>,
+    ) -> Option<IsotopeTable> {
+        let mut table = IsotopeTable::default();
+        for adduct in adducts.iter() {
+            let (pattern, mono) = match (adduct.pattern(formula), adduct.mz(formula)) {
+                (Some(pattern), Some(mono)) => (pattern, mono),
+                _ => continue,
+            };
+            let pattern = match resolution {
+                Some(resolution) => pattern.centroid(resolution)?,
+                None => pattern,
+            };
+            let z = adduct.charge.abs().max(1) as f64;
+            let intensities = pattern.relative_intensities();
+            for (peak, intensity) in pattern.peaks().iter().zip(intensities) {
+                if intensity < MIN_TABLE_INTENSITY {
+                    continue;
+                }
+                table.adduct.push(adduct.name.clone());
+                table.charge.push(adduct.charge);
+                table.offset.push(((peak.mass - mono) * z).round() as i32);
+                table.mz.push(peak.mass);
+                table.probability.push(peak.probability);
+                table.intensity.push(intensity);
+            }
+        }
+        Some(table)
+    }
+
+    pub fn len(&self) -> usize {
+        self.mz.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mz.is_empty()
+    }
+
+    pub fn to_dataframe(&self) -> PolarsResult<DataFrame> {
+        df!(
+            "adduct" => &self.adduct,
+            "charge" => &self.charge,
+            "offset" => &self.offset,
+            "mz" => &self.mz,
+            "probability" => &self.probability,
+            "intensity" => &self.intensity
+        )
+    }
+}
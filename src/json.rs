@@ -1,26 +1,30 @@
+use crate::periodic_table;
 use crate::Molecule;
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonBase {
+    #[serde(alias = "commonchem")]
     pub rdkitjson: VersionInfo,
+    #[serde(default)]
     pub defaults: RdkitDefaults,
     pub molecules: Vec<JsonMolecule>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VersionInfo {
     pub version: i32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RdkitDefaults {
     pub atom: AtomDefaults,
     pub bond: BondDefaults,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AtomDefaults {
     pub z: i32,
     pub impHs: i32,
@@ -30,13 +34,13 @@ pub struct AtomDefaults {
     pub stereo: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BondDefaults {
     pub bo: i32,
     pub stereo: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonMolecule {
     #[serde(default)]
     pub name: String,
@@ -44,16 +48,20 @@ pub struct JsonMolecule {
     pub bonds: Vec<JsonBond>,
     #[serde(default)]
     pub conformers: Vec<JsonConformer>,
+    /// Molecule properties, e.g. SD data fields of a molecule built from JSON
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Value>,
+    #[serde(default)]
     pub extensions: Vec<Extensions>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonConformer {
     pub coords: Vec<Vec<f32>>,
-    dim: i32,
+    pub dim: i32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Extensions {
     name: String,
     formatVersion: i32,
@@ -66,32 +74,37 @@ pub struct Extensions {
     atomRings: Vec<Vec<i32>>,
     #[serde(default)]
     cipCodes: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cipRanks: Vec<i32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonAtom {
     #[serde(default)]
-    chg: i32,
-    #[serde(default)]
-    impHs: i32,
+    pub chg: i32,
     #[serde(default)]
-    isotope: i32,
+    pub impHs: i32,
     #[serde(default)]
-    nRad: i32,
+    pub isotope: i32,
     #[serde(default)]
-    stereo: String,
+    pub nRad: i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stereo: String,
     #[serde(default = "z_default")]
-    z: i32,
+    pub z: i32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JsonBond {
     #[serde(default)]
-    atoms: Vec<i32>,
-    #[serde(default)]
-    bo: i32,
+    pub atoms: Vec<i32>,
+    #[serde(default = "bo_default")]
+    pub bo: i32,
     #[serde(default = "stereo_default")]
-    stereo: String,
+    pub stereo: String,
+    /// Reference atoms of cis/trans stereo, a neighbour of the first and of the second bond atom
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stereoAtoms: Vec<i32>,
 }
 
 const Z_DEFAULT: i32 = 6;
@@ -99,28 +112,141 @@ fn z_default() -> i32 {
     Z_DEFAULT
 }
 
+const BO_DEFAULT: i32 = 1;
+fn bo_default() -> i32 {
+    BO_DEFAULT
+}
+
 fn stereo_default() -> String {
     String::from("unspecified")
 }
 
+/// Version written by to_json, accepted by all RDKit versions reading CommonChem
+const RDKIT_JSON_VERSION: i32 = 10;
+
+impl Default for RdkitDefaults {
+    /// The defaults RDKit uses when writing molecules
+    fn default() -> Self {
+        RdkitDefaults {
+            atom: AtomDefaults {
+                z: Z_DEFAULT,
+                impHs: 0,
+                chg: 0,
+                nRad: 0,
+                isotope: 0,
+                stereo: stereo_default(),
+            },
+            bond: BondDefaults {
+                bo: BO_DEFAULT,
+                stereo: stereo_default(),
+            },
+        }
+    }
+}
+
+impl JsonBase {
+    /// Wraps molecules with the RDKit default atom and bond values
+    pub fn from_molecules(molecules: Vec<JsonMolecule>) -> JsonBase {
+        JsonBase {
+            rdkitjson: VersionInfo {
+                version: RDKIT_JSON_VERSION,
+            },
+            defaults: RdkitDefaults::default(),
+            molecules,
+        }
+    }
+
+    /// Parses an RDKit or CommonChem JSON document with any number of molecules
+    pub fn from_json(json_str: &str) -> Result<JsonBase, serde_json::Error> {
+        serde_json::from_str(json_str)
+    }
+
+    /// Serializes to CommonChem JSON, which can be passed to `Molecule::new`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl JsonAtom {
+    /// An atom of the given atomic number without charge and implicit hydrogens
+    pub fn new(z: i32) -> JsonAtom {
+        JsonAtom {
+            chg: 0,
+            impHs: 0,
+            isotope: 0,
+            nRad: 0,
+            stereo: String::new(),
+            z,
+        }
+    }
+
+    /// The element symbol, `*` for dummy atoms and None for invalid atomic numbers
+    pub fn symbol(&self) -> Option<&'static str> {
+        periodic_table::symbol(self.z)
+    }
+}
+
+impl JsonBond {
+    /// A bond between two atom indices, bond orders are 0 to 3
+    pub fn new(begin: i32, end: i32, bo: i32) -> JsonBond {
+        JsonBond {
+            atoms: vec![begin, end],
+            bo,
+            stereo: stereo_default(),
+            stereoAtoms: Vec::new(),
+        }
+    }
+}
+
+impl JsonConformer {
+    /// A conformer with one coordinate vector per atom, dim is 2 or 3
+    pub fn new(coords: Vec<Vec<f32>>, dim: i32) -> JsonConformer {
+        JsonConformer { coords, dim }
+    }
+}
+
 impl JsonMolecule {
     pub fn new(molstring: &str) -> JsonMolecule {
         JsonMolecule::json_mol_from_string(molstring, "")
     }
 
+    /// Panics if RDKit can not parse molstring, use `Molecule::new` and `get_json_molecule` for untrusted
+    /// input. The JSON RDKit writes for a parsed molecule always holds that molecule, reading it back can not
+    /// fail.
     pub fn json_mol_from_string(molstring: &str, json_info: &str) -> JsonMolecule {
         let json_str = jsonfrom_string(molstring);
-        JsonMolecule::json_mol_from_json(&json_str)
+        JsonMolecule::json_mol_from_json(&json_str).expect("RDKit wrote no molecule")
     }
 
     pub fn json_mol_from_smiles(smiles: &str) -> JsonMolecule {
         JsonMolecule::json_mol_from_string(smiles, "")
     }
 
-    pub fn json_mol_from_json(json_str: &str) -> JsonMolecule {
-        let rdkit_json: JsonBase = serde_json::from_str(&json_str).expect("Wrong JSON format!?");
-        let mol = serde_json::to_string(&rdkit_json.molecules[0]).unwrap();
-        serde_json::from_str(&mol).expect("Wrong JSON format!?")
+    /// The first molecule of a JSON document, an error if the document is invalid or has no molecules
+    pub fn json_mol_from_json(json_str: &str) -> Result<JsonMolecule, serde_json::Error> {
+        JsonMolecule::json_mols_from_json(json_str)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| serde::de::Error::custom("No molecule in JSON"))
+    }
+
+    /// All molecules of a JSON document
+    pub fn json_mols_from_json(json_str: &str) -> Result<Vec<JsonMolecule>, serde_json::Error> {
+        Ok(JsonBase::from_json(json_str)?.molecules)
+    }
+
+    /// Conformer i, None if there is no such conformer
+    pub fn conformer(&self, i: usize) -> Option<&JsonConformer> {
+        self.conformers.get(i)
+    }
+
+    pub fn num_conformers(&self) -> usize {
+        self.conformers.len()
+    }
+
+    /// Serializes the molecule as CommonChem JSON, which can be passed to `Molecule::new`
+    pub fn to_json(&self) -> String {
+        JsonBase::from_molecules(vec![self.clone()]).to_json()
     }
 }
 
@@ -128,3 +254,368 @@ pub fn jsonfrom_string(input: &str) -> String {
     let pkl_mol = Molecule::new(input).unwrap();
     pkl_mol.get_json("")
 }
+
+/// Chemical elements by atomic number, `Dummy` is the RDKit dummy atom `*` with z = 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[repr(u8)]
+#[rustfmt::skip]
+pub enum Element {
+    Dummy,
+    H, He,
+    Li, Be, B, C, N, O, F, Ne,
+    Na, Mg, Al, Si, P, S, Cl, Ar,
+    K, Ca, Sc, Ti, V, Cr, Mn, Fe, Co, Ni, Cu, Zn, Ga, Ge, As, Se, Br, Kr,
+    Rb, Sr, Y, Zr, Nb, Mo, Tc, Ru, Rh, Pd, Ag, Cd, In, Sn, Sb, Te, I, Xe,
+    Cs, Ba, La, Ce, Pr, Nd, Pm, Sm, Eu, Gd, Tb, Dy, Ho, Er, Tm, Yb, Lu,
+    Hf, Ta, W, Re, Os, Ir, Pt, Au, Hg, Tl, Pb, Bi, Po, At, Rn,
+    Fr, Ra, Ac, Th, Pa, U, Np, Pu, Am, Cm, Bk, Cf, Es, Fm, Md, No, Lr,
+    Rf, Db, Sg, Bh, Hs, Mt, Ds, Rg, Cn, Nh, Fl, Mc, Lv, Ts, Og,
+}
+
+// the variants are numbered like the periodic table, so each atomic number with element data is a variant
+const _: () = assert!(Element::Og as usize + 1 == periodic_table::ELEMENT_DATA.len());
+
+impl Element {
+    /// The element with atomic number z, None outside of 0..=118
+    pub fn from_z(z: i32) -> Option<Element> {
+        let data = periodic_table::element(z)?;
+        // data.z is at most Og, the last variant
+        Some(unsafe { std::mem::transmute::<u8, Element>(data.z) })
+    }
+
+    /// Looks up an element by its case sensitive symbol, `*` is the dummy atom
+    pub fn from_symbol(symbol: &str) -> Option<Element> {
+        Element::from_z(periodic_table::from_symbol(symbol)?.z as i32)
+    }
+
+    pub fn z(self) -> u8 {
+        self as u8
+    }
+
+    /// Masses, valences and radius of the element
+    pub fn data(self) -> &'static periodic_table::ElementData {
+        &periodic_table::ELEMENT_DATA[self as usize]
+    }
+
+    pub fn symbol(self) -> &'static str {
+        self.data().symbol
+    }
+
+    pub fn name(self) -> &'static str {
+        self.data().name
+    }
+}
+
+/// Tetrahedral stereo of an atom as written by RDKit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtomStereo {
+    Unspecified,
+    /// clockwise
+    Cw,
+    /// counterclockwise
+    Ccw,
+    Other,
+}
+
+impl AtomStereo {
+    fn from_json(stereo: &str) -> AtomStereo {
+        match stereo {
+            "" | "unspecified" => AtomStereo::Unspecified,
+            "cw" => AtomStereo::Cw,
+            "ccw" => AtomStereo::Ccw,
+            _ => AtomStereo::Other,
+        }
+    }
+}
+
+/// Double bond stereo as written by RDKit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BondStereo {
+    Unspecified,
+    Cis,
+    Trans,
+    /// explicitly unknown, e.g. a crossed double bond
+    Either,
+}
+
+impl BondStereo {
+    fn from_json(stereo: &str) -> BondStereo {
+        match stereo {
+            "cis" => BondStereo::Cis,
+            "trans" => BondStereo::Trans,
+            "either" => BondStereo::Either,
+            _ => BondStereo::Unspecified,
+        }
+    }
+}
+
+/// Bond order of the (kekulized) JSON representation, aromaticity is flagged separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BondOrder {
+    Zero,
+    Single,
+    Double,
+    Triple,
+    Other(i32),
+}
+
+impl BondOrder {
+    pub fn from_bo(bo: i32) -> BondOrder {
+        match bo {
+            0 => BondOrder::Zero,
+            1 => BondOrder::Single,
+            2 => BondOrder::Double,
+            3 => BondOrder::Triple,
+            bo => BondOrder::Other(bo),
+        }
+    }
+}
+
+/// Typed view of an atom
+#[derive(Clone, Debug, PartialEq)]
+pub struct Atom {
+    pub index: usize,
+    /// None for atomic numbers outside of the periodic table
+    pub element: Option<Element>,
+    pub formal_charge: i32,
+    pub implicit_hs: u32,
+    /// None for the natural isotope mixture
+    pub isotope: Option<u32>,
+    pub radicals: u32,
+    pub stereo: AtomStereo,
+    pub is_aromatic: bool,
+    pub is_in_ring: bool,
+}
+
+/// Typed view of a bond
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bond {
+    pub index: usize,
+    pub begin: usize,
+    pub end: usize,
+    pub order: BondOrder,
+    pub stereo: BondStereo,
+    pub is_aromatic: bool,
+    pub is_in_ring: bool,
+}
+
+const RDKIT_EXTENSION: &str = "rdkitRepresentation";
+
+/// CIP stereo descriptor, lower case pseudo asymmetry labels are kept as Pseudo variants
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CipLabel {
+    R,
+    S,
+    PseudoR,
+    PseudoS,
+    E,
+    Z,
+}
+
+impl CipLabel {
+    pub fn from_code(code: &str) -> Option<CipLabel> {
+        match code {
+            "R" => Some(CipLabel::R),
+            "S" => Some(CipLabel::S),
+            "r" => Some(CipLabel::PseudoR),
+            "s" => Some(CipLabel::PseudoS),
+            "E" => Some(CipLabel::E),
+            "Z" => Some(CipLabel::Z),
+            _ => None,
+        }
+    }
+}
+
+/// Typed content of the `rdkitRepresentation` extension, atom and bond indices refer to the molecule
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdkitExtension {
+    pub format_version: i32,
+    pub toolkit_version: String,
+    pub aromatic_atoms: BTreeSet<usize>,
+    pub aromatic_bonds: BTreeSet<usize>,
+    /// Smallest set of smallest rings, atoms in ring order
+    pub atom_rings: Vec<Vec<usize>>,
+    /// R/S labels of stereo centres
+    pub atom_cip: BTreeMap<usize, CipLabel>,
+    /// E/Z labels of stereo double bonds
+    pub bond_cip: BTreeMap<usize, CipLabel>,
+}
+
+impl RdkitExtension {
+    fn from_extension(ext: &Extensions, bonds: &[JsonBond]) -> RdkitExtension {
+        let indices = |v: &[i32]| -> Vec<usize> {
+            v.iter().filter(|&&i| i >= 0).map(|&i| i as usize).collect()
+        };
+        // entries are [atom index, code]
+        let atom_cip: BTreeMap<usize, CipLabel> = ext
+            .cipCodes
+            .iter()
+            .filter_map(|entry| {
+                let index = entry.get(0)?.as_u64()? as usize;
+                let label = CipLabel::from_code(entry.get(1)?.as_str()?)?;
+                Some((index, label))
+            })
+            .collect();
+        RdkitExtension {
+            format_version: ext.formatVersion,
+            toolkit_version: ext.toolkitVersion.clone(),
+            aromatic_atoms: indices(&ext.aromaticAtoms).into_iter().collect(),
+            aromatic_bonds: indices(&ext.aromaticBonds).into_iter().collect(),
+            atom_rings: ext.atomRings.iter().map(|r| indices(r)).collect(),
+            atom_cip,
+            bond_cip: bond_cip_labels(bonds, &ext.cipRanks),
+        }
+    }
+
+    pub fn is_ring_atom(&self, atom: usize) -> bool {
+        self.atom_rings.iter().any(|r| r.contains(&atom))
+    }
+
+    /// Atom pairs (lower index first) which are adjacent in a ring
+    pub fn ring_bonds(&self) -> HashSet<(usize, usize)> {
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+        for ring in self.atom_rings.iter() {
+            for (i, &a) in ring.iter().enumerate() {
+                let b = ring[(i + 1) % ring.len()];
+                pairs.insert((a.min(b), a.max(b)));
+            }
+        }
+        pairs
+    }
+
+    /// Fused aromatic ring systems as sorted atom lists, rings sharing a bond belong to the same system
+    pub fn aromatic_systems(&self) -> Vec<Vec<usize>> {
+        let mut systems: Vec<BTreeSet<usize>> = Vec::new();
+        let aromatic_rings = self
+            .atom_rings
+            .iter()
+            .filter(|r| r.iter().all(|a| self.aromatic_atoms.contains(a)));
+        for ring in aromatic_rings {
+            let mut merged: BTreeSet<usize> = ring.iter().cloned().collect();
+            systems.retain(|system| {
+                if system.intersection(&merged).count() >= 2 {
+                    merged.extend(system.iter().cloned());
+                    false
+                } else {
+                    true
+                }
+            });
+            systems.push(merged);
+        }
+        systems
+            .into_iter()
+            .map(|s| s.into_iter().collect())
+            .collect()
+    }
+}
+
+/// Derives E/Z from cis/trans stereo, comparing the reference atoms with the highest ranked neighbours
+fn bond_cip_labels(bonds: &[JsonBond], cip_ranks: &[i32]) -> BTreeMap<usize, CipLabel> {
+    let mut labels: BTreeMap<usize, CipLabel> = BTreeMap::new();
+    if cip_ranks.is_empty() {
+        return labels;
+    }
+    // the highest ranked neighbour of atom, excluding the other double bond atom, None on ties
+    let top_neighbour = |atom: i32, other: i32| -> Option<i32> {
+        let mut ranked: Vec<(i32, i32)> = bonds
+            .iter()
+            .filter(|b| b.atoms.len() == 2 && b.atoms.contains(&atom))
+            .map(|b| {
+                if b.atoms[0] == atom {
+                    b.atoms[1]
+                } else {
+                    b.atoms[0]
+                }
+            })
+            .filter(|&n| n != other)
+            .map(|n| (*cip_ranks.get(n as usize).unwrap_or(&-1), n))
+            .collect();
+        ranked.sort();
+        match ranked.as_slice() {
+            [] => None,
+            [.., (r1, _), (r2, _)] if r1 == r2 => None,
+            [.., (_, n)] => Some(*n),
+        }
+    };
+    for (i, bond) in bonds.iter().enumerate() {
+        let cis = match bond.stereo.as_str() {
+            "cis" => true,
+            "trans" => false,
+            _ => continue,
+        };
+        if bond.atoms.len() != 2 || bond.stereoAtoms.len() != 2 {
+            continue;
+        }
+        let (a, b) = (bond.atoms[0], bond.atoms[1]);
+        let (top_a, top_b) = match (top_neighbour(a, b), top_neighbour(b, a)) {
+            (Some(top_a), Some(top_b)) => (top_a, top_b),
+            _ => continue,
+        };
+        // an odd number of reference atoms which are not the top ranked neighbours inverts cis/trans
+        let swapped = (bond.stereoAtoms[0] != top_a) != (bond.stereoAtoms[1] != top_b);
+        let label = if cis != swapped {
+            CipLabel::Z
+        } else {
+            CipLabel::E
+        };
+        labels.insert(i, label);
+    }
+    labels
+}
+
+impl JsonMolecule {
+    /// The typed rdkit extension, None for molecules not written by RDKit
+    pub fn rdkit_representation(&self) -> Option<RdkitExtension> {
+        self.extensions
+            .iter()
+            .find(|e| e.name == RDKIT_EXTENSION)
+            .map(|e| RdkitExtension::from_extension(e, &self.bonds))
+    }
+
+    /// Typed atoms, aromaticity and ring membership are taken from the rdkit extension
+    pub fn atom_views(&self) -> Vec<Atom> {
+        let ext = self.rdkit_representation().unwrap_or_default();
+        self.atoms
+            .iter()
+            .enumerate()
+            .map(|(i, a)| Atom {
+                index: i,
+                element: Element::from_z(a.z),
+                formal_charge: a.chg,
+                implicit_hs: a.impHs.max(0) as u32,
+                isotope: if a.isotope > 0 {
+                    Some(a.isotope as u32)
+                } else {
+                    None
+                },
+                radicals: a.nRad.max(0) as u32,
+                stereo: AtomStereo::from_json(&a.stereo),
+                is_aromatic: ext.aromatic_atoms.contains(&i),
+                is_in_ring: ext.is_ring_atom(i),
+            })
+            .collect()
+    }
+
+    /// Typed bonds, aromaticity and ring membership are taken from the rdkit extension
+    pub fn bond_views(&self) -> Vec<Bond> {
+        let ext = self.rdkit_representation().unwrap_or_default();
+        let ring_bonds = ext.ring_bonds();
+        self.bonds
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                let begin = b.atoms.first().cloned().unwrap_or(0).max(0) as usize;
+                let end = b.atoms.get(1).cloned().unwrap_or(0).max(0) as usize;
+                Bond {
+                    index: i,
+                    begin,
+                    end,
+                    order: BondOrder::from_bo(b.bo),
+                    stereo: BondStereo::from_json(&b.stereo),
+                    is_aromatic: ext.aromatic_bonds.contains(&i),
+                    is_in_ring: ring_bonds.contains(&(begin.min(end), begin.max(end))),
+                }
+            })
+            .collect()
+    }
+}
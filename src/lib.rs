@@ -13,6 +13,7 @@
 //! **IMPORTANT**: The `Molecule` struct contains raw pointers and is not `Send` or `Sync`, so it cannot
 //! be safely shared between threads. However, RDKit CFFI itself is thread-safe when each thread
 //! creates its own molecules. For parallel processing, create separate `Molecule` instances in each thread.
+//! SD and SMILES files can be parsed in a thread pool with [`parallel`].
 //!
 //! [github repository](https://github.com/chrissly31415/rdkitcffi).
 //!
@@ -39,13 +40,13 @@
 //! let mol = Molecule::new_with_args("c1cc(O[H])ccc1", json_args).unwrap();
 //! ```
 //!
-//! Working with SD files and filtering invalid molecules (=None):
+//! Working with SD files and filtering invalid molecules (=Err):
 //!
 //! ```
-//!use rdkitcffi::{Molecule,read_sdfile};
+//!use rdkitcffi::{Molecule,RecordError,read_sdfile};
 //!
-//! let mut mol_opt_list : Vec<Option<Molecule>>= read_sdfile("data/test.sdf");
-//! let mut mol_list: Vec<Molecule> = mol_opt_list.into_iter().filter_map(|m| m).collect();
+//! let mut mol_res_list : Vec<Result<Molecule, RecordError>>= read_sdfile("data/test.sdf");
+//! let mut mol_list: Vec<Molecule> = mol_res_list.into_iter().filter_map(|m| m.ok()).collect();
 //! mol_list.iter_mut().for_each(|m| m.remove_all_hs());
 //!
 //! ```
@@ -104,7 +105,7 @@
 //! let mut mol = Molecule::new("CCCN").unwrap();
 //! mol.add_hs();
 //! mol.set_3d_coords("");
-//! let coords: Vec<Vec<f32>> = mol.get_coords();
+//! let coords: Vec<Vec<f32>> = mol.get_coords().unwrap();
 //!
 //! ```
 //!
@@ -127,7 +128,6 @@ use std::collections::HashMap;
 
 use libc;
 use std::ffi::{CStr, CString};
-use std::fs::read_to_string;
 use std::mem;
 use std::os::raw::{c_char, c_short, c_void};
 
@@ -155,9 +155,38 @@ use bindings::{
     get_rdkit_fp_as_bytes,
 };
 
+pub mod align;
+pub mod cluster;
+pub mod compression;
+pub mod error;
+pub mod fingerprint;
+pub mod formula;
+pub mod fpdb;
+pub mod fps;
+pub mod geometry;
+pub mod graph;
+pub mod isotope_pattern;
+pub mod parallel;
+pub mod periodic_table;
+pub mod picker;
+pub mod sdf;
+pub mod sdindex;
+pub mod smiles;
+pub mod substruct;
+
 pub mod json;
 // Re-export commonly used types from json module
-pub use json::{JsonAtom, JsonBase, JsonBond, JsonConformer, JsonMolecule};
+pub use json::{
+    Atom, AtomStereo, Bond, BondOrder, BondStereo, CipLabel, Element, JsonAtom, JsonBase, JsonBond,
+    JsonConformer, JsonMolecule, RdkitExtension,
+};
+
+pub use align::{Alignment, AtomSelection};
+pub use error::{CoordsError, RecordError};
+pub use formula::Formula;
+pub use graph::MolGraph;
+pub use sdf::{SDIterator, SDRecord, SDWriter};
+pub use sdindex::IndexedSDReader;
 
 /// Basic class, implementing most functionality as member functions of a molecule object
 
@@ -187,6 +216,12 @@ impl Molecule {
 
     /// Constructor returning an optional molecule with custom JSON configuration
     pub fn new_with_args(input: &str, json_info: &str) -> Option<Molecule> {
+        let _guard = error::parse_lock();
+        Self::parse_unlocked(input, json_info)
+    }
+
+    /// Parses without taking the lock, for callers which already hold it
+    pub(crate) fn parse_unlocked(input: &str, json_info: &str) -> Option<Molecule> {
         unsafe {
             let input_cstr = match CString::new(input) {
                 Ok(s) => s,
@@ -232,7 +267,10 @@ impl Molecule {
             panic!("Memory allocation failed!");
         }
 
-        let pkl_mol = unsafe { get_mol(input_cstr.as_ptr(), pkl_size, json_info.as_ptr()) };
+        let pkl_mol = {
+            let _guard = error::parse_lock();
+            unsafe { get_mol(input_cstr.as_ptr(), pkl_size, json_info.as_ptr()) }
+        };
         if pkl_mol.is_null() {
             unsafe {
                 libc::free(pkl_size as *mut c_void);
@@ -262,10 +300,19 @@ impl Molecule {
         res
     }
 
-    /// Gets the underlying Molecule object of the common chem structure
+    /// Gets the underlying Molecule object of the common chem structure. This does not fail, RDKit writes
+    /// every molecule as a CommonChem document holding exactly that molecule.
     pub fn get_json_molecule(&self) -> JsonMolecule {
         let json_repr = self.get_json("");
-        JsonMolecule::json_mol_from_json(&json_repr)
+        JsonMolecule::json_mol_from_json(&json_repr).expect("RDKit wrote no molecule")
+    }
+
+    /// Creates a molecule from a (possibly edited or Rust built) common chem structure.
+    /// RDKit specific extensions are not used, aromaticity and rings are perceived from the atoms and bonds.
+    pub fn from_json_molecule(json_mol: &JsonMolecule) -> Option<Molecule> {
+        let mut json_mol = json_mol.clone();
+        json_mol.extensions.clear();
+        Molecule::new(&json_mol.to_json())
     }
 
     pub fn get_atoms(&self) -> Vec<JsonAtom> {
@@ -283,16 +330,148 @@ impl Molecule {
         json_mol.bonds
     }
 
+    /// Aromaticity, rings and CIP labels as perceived by RDKit
+    pub fn get_rdkit_extension(&self) -> Option<RdkitExtension> {
+        self.get_json_molecule().rdkit_representation()
+    }
+
+    /// Graph view for neighbours, ring membership and topological distances
+    pub fn graph(&self) -> MolGraph {
+        MolGraph::new(&self.get_json_molecule())
+    }
+
+    /// Molecular formula in Hill order including implicit hydrogens, e.g. `mol.formula().to_string()`
+    pub fn formula(&self) -> Formula {
+        Formula::new(&self.get_json_molecule())
+    }
+
+    /// Monoisotopic mass from the most abundant isotopes, or the labelled ones
+    pub fn exact_mass(&self) -> f64 {
+        self.formula().monoisotopic_mass()
+    }
+
+    /// Typed atoms with aromaticity and ring flags
+    pub fn atoms(&self) -> Vec<Atom> {
+        self.get_json_molecule().atom_views()
+    }
+
+    /// Typed bonds with aromaticity and ring flags
+    pub fn bonds(&self) -> Vec<Bond> {
+        self.get_json_molecule().bond_views()
+    }
+
     pub fn get_numbonds(&self) -> usize {
         let json_mol = self.get_json_molecule();
         json_mol.bonds.len()
     }
 
-    /// Get a 2 dimensional vector with atomic coordinates
-    pub fn get_coords(&self) -> Vec<Vec<f32>> {
+    /// Get a 2 dimensional vector with the atomic coordinates of the first conformer, None without conformers
+    pub fn get_coords(&self) -> Option<Vec<Vec<f32>>> {
+        self.conformer(0).map(|c| c.coords)
+    }
+
+    /// Conformer i with its coordinates and dimension
+    pub fn conformer(&self, i: usize) -> Option<JsonConformer> {
+        let json_mol = self.get_json_molecule();
+        json_mol.conformers.into_iter().nth(i)
+    }
+
+    pub fn num_conformers(&self) -> usize {
+        self.get_json_molecule().num_conformers()
+    }
+
+    /// RMSD between conformers probe and reference without moving them, None if a conformer is missing or
+    /// the selection refers to atoms which do not exist
+    pub fn conformer_rmsd(
+        &self,
+        probe: usize,
+        reference: usize,
+        selection: &AtomSelection,
+    ) -> Option<f64> {
         let json_mol = self.get_json_molecule();
-        let conf: &JsonConformer = json_mol.conformers.get(0).unwrap();
-        conf.coords.to_owned()
+        let probe_points = geometry::points(json_mol.conformer(probe)?);
+        let reference_points = geometry::points(json_mol.conformer(reference)?);
+        align::rmsd(
+            &probe_points,
+            &reference_points,
+            &selection.pairs(&json_mol),
+        )
+    }
+
+    /// Superimposes conformer probe onto conformer reference, the alignment holds the RMSD after the fit and
+    /// the moved coordinates of all atoms
+    pub fn align_conformers(
+        &self,
+        probe: usize,
+        reference: usize,
+        selection: &AtomSelection,
+    ) -> Option<Alignment> {
+        let json_mol = self.get_json_molecule();
+        let probe_points = geometry::points(json_mol.conformer(probe)?);
+        let reference_points = geometry::points(json_mol.conformer(reference)?);
+        align::superpose(
+            &probe_points,
+            &reference_points,
+            &selection.pairs(&json_mol),
+        )
+    }
+
+    /// Superimposes the first conformer onto the first conformer of another molecule, e.g. a second
+    /// embedding of the same molecule or a different molecule paired via `AtomSelection::Map`
+    pub fn align_to(&self, reference: &Molecule, selection: &AtomSelection) -> Option<Alignment> {
+        let json_mol = self.get_json_molecule();
+        let probe_points = geometry::points(json_mol.conformer(0)?);
+        let reference_points = geometry::points(reference.get_json_molecule().conformer(0)?);
+        align::superpose(
+            &probe_points,
+            &reference_points,
+            &selection.pairs(&json_mol),
+        )
+    }
+
+    /// Replaces the coordinates of the first conformer or adds one, e.g. after an external optimization
+    pub fn set_coords(&mut self, coords: &[[f32; 3]]) -> Result<(), CoordsError> {
+        self.set_conformer_coords(0, coords)
+    }
+
+    /// Replaces the coordinates of conformer i, i equal to the number of conformers adds a conformer.
+    /// 2D conformers stay 2D if all z coordinates are 0.
+    ///
+    /// The molecule is rebuilt from its `JsonMolecule` like in `from_json_molecule`: the name, properties,
+    /// atoms, bonds and other conformers are kept, atom and bond properties are not part of the JSON
+    /// molecule and are lost.
+    pub fn set_conformer_coords(
+        &mut self,
+        i: usize,
+        coords: &[[f32; 3]],
+    ) -> Result<(), CoordsError> {
+        let mut json_mol = self.get_json_molecule();
+        if coords.len() != json_mol.atoms.len() {
+            return Err(CoordsError::AtomCount {
+                atoms: json_mol.atoms.len(),
+                coords: coords.len(),
+            });
+        }
+        let num_conformers = json_mol.num_conformers();
+        if i > num_conformers {
+            return Err(CoordsError::NoConformer {
+                index: i,
+                conformers: num_conformers,
+            });
+        }
+        let flat = coords.iter().all(|c| c[2] == 0.0);
+        let conformer = if flat && json_mol.conformer(i).is_some_and(|c| c.dim == 2) {
+            JsonConformer::new(coords.iter().map(|c| vec![c[0], c[1]]).collect(), 2)
+        } else {
+            JsonConformer::new(coords.iter().map(|c| c.to_vec()).collect(), 3)
+        };
+        if i == num_conformers {
+            json_mol.conformers.push(conformer);
+        } else {
+            json_mol.conformers[i] = conformer;
+        }
+        *self = Molecule::from_json_molecule(&json_mol).ok_or(CoordsError::Rebuild)?;
+        Ok(())
     }
 
     /// Get the SMILES string from a molecule
@@ -715,7 +894,10 @@ impl Molecule {
             return None;
         }
 
-        let pkl_mol = unsafe { get_qmol(input_cstr.as_ptr(), pkl_size, json_info.as_ptr()) };
+        let pkl_mol = {
+            let _guard = error::parse_lock();
+            unsafe { get_qmol(input_cstr.as_ptr(), pkl_size, json_info.as_ptr()) }
+        };
         if pkl_mol.is_null() {
             unsafe {
                 libc::free(pkl_size as *mut c_void);
@@ -725,6 +907,11 @@ impl Molecule {
         return Some(Molecule { pkl_size, pkl_mol });
     }
 
+    /// The pickled molecule as used by the cffi functions
+    pub(crate) fn pickle(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.pkl_mol as *const u8, *self.pkl_size) }
+    }
+
     fn free_memory(&mut self) {
         unsafe {
             // Free molecule data if pointer is not null
@@ -747,79 +934,59 @@ pub fn prefer_coordgen(val: bool) {
     unsafe { bindings::prefer_coordgen(val as c_short) }
 }
 
-/// read a classical .smi file
-pub fn read_smifile(smi_file: &str) -> Vec<Option<Molecule>> {
-    let smi_file = read_to_string(smi_file).expect("Could not load file.");
-    let mut mol_list: Vec<Option<Molecule>> = Vec::new();
-    let smiles_list: Vec<&str> = smi_file.split("\n").collect();
-    for s in smiles_list.iter() {
-        let s_mod = s.trim();
-        if s_mod.len() == 0 {
-            mol_list.push(None);
-            continue;
-        };
-        let mol_opt = Molecule::new(s_mod);
-        mol_list.push(mol_opt);
+/// Get the version of the RDKit library
+pub fn rdkit_version() -> String {
+    unsafe {
+        let version_cchar = bindings::version();
+        let res = CStr::from_ptr(version_cchar).to_string_lossy().into_owned();
+        free_ptr(version_cchar);
+        res
     }
-    mol_list
 }
 
-/// read a classical .smi file, filter molecules which are none
-pub fn read_smifile_unwrap(smi_file: &str) -> Vec<Molecule> {
-    let mol_opt_list: Vec<Option<Molecule>> = crate::read_smifile(smi_file);
-    let mol_list: Vec<Molecule> = mol_opt_list.into_iter().filter_map(|m| m).collect();
-    mol_list
-}
-
-/// read a classical .sdf file
-pub fn read_sdfile(sd_file: &str) -> Vec<Option<Molecule>> {
-    let sd_file = read_to_string(sd_file).expect("Could not load file.");
-    let mut mol_list: Vec<Option<Molecule>> = Vec::new();
-    let molblock_list: Vec<&str> = sd_file.split("$$$$").collect();
-    for (_i, s) in molblock_list.iter().enumerate() {
+/// read a classical .smi file, which may be gzip or zstd compressed
+///
+/// Blank lines are skipped, failed records carry their index, line number, name and the RDKit error message
+pub fn read_smifile(smi_file: &str) -> Vec<Result<Molecule, RecordError>> {
+    let smi_file = compression::read_to_string(smi_file).expect("Could not load file.");
+    let mut mol_list: Vec<Result<Molecule, RecordError>> = Vec::new();
+    for (i, s) in smi_file.split("\n").enumerate() {
         let s_mod = s.trim();
         if s_mod.len() == 0 {
-            mol_list.push(None);
             continue;
         };
-        let mol_opt = Molecule::new(s_mod);
-        mol_list.push(mol_opt);
+        let index = mol_list.len();
+        let mol_res = error::parse_input(s_mod, "").map_err(|message| {
+            let name = s_mod
+                .split_once(char::is_whitespace)
+                .map_or("", |(_, n)| n.trim());
+            error::record_error(index, i + 1, name, message)
+        });
+        mol_list.push(mol_res);
     }
     mol_list
 }
 
-/// read a classical .sdf file, filter molecules which are none
-pub fn read_sdfile_unwrap(sd_file: &str) -> Vec<Molecule> {
-    let mol_opt_list: Vec<Option<Molecule>> = crate::read_sdfile(sd_file);
-    let mol_list: Vec<Molecule> = mol_opt_list.into_iter().filter_map(|m| m).collect();
+/// read a classical .smi file, filter molecules which could not be parsed
+pub fn read_smifile_unwrap(smi_file: &str) -> Vec<Molecule> {
+    let mol_res_list: Vec<Result<Molecule, RecordError>> = crate::read_smifile(smi_file);
+    let mol_list: Vec<Molecule> = mol_res_list.into_iter().filter_map(|m| m.ok()).collect();
     mol_list
 }
 
-pub struct SDIterator {
-    molblock_iterator: std::vec::IntoIter<String>,
+/// read a classical .sdf file, which may be gzip or zstd compressed
+///
+/// Empty blocks between `$$$$` lines are skipped, failed records carry their index, starting line, title and
+/// the RDKit error message
+pub fn read_sdfile(sd_file: &str) -> Vec<Result<Molecule, RecordError>> {
+    SDIterator::new(sd_file)
+        .map(|record| record.into_result())
+        .collect()
 }
 
-impl SDIterator {
-    pub fn new(pathname: &str) -> Self {
-        let sd_file = read_to_string(pathname).expect("Could not load file.");
-        let molblock_list: Vec<String> = sd_file.split("$$$$").map(|x| x.to_string()).collect();
-        let molblock_iterator = molblock_list.into_iter();
-        SDIterator { molblock_iterator }
-    }
-}
-
-impl Iterator for SDIterator {
-    type Item = Option<Molecule>;
-    fn next(&mut self) -> Option<Self::Item> {
-        let molblock = self.molblock_iterator.next();
-        let s_mod = match molblock {
-            Some(molblock) => molblock.trim().to_string(),
-            None => return None,
-        };
-        if s_mod.is_empty() {
-            return self.next(); // skip empty delimiter blocks
-        }
-        let mol_opt = Molecule::new(&s_mod);
-        Some(mol_opt)
-    }
+/// read a classical .sdf file, filter molecules which could not be parsed
+pub fn read_sdfile_unwrap(sd_file: &str) -> Vec<Molecule> {
+    let mol_res_list: Vec<Result<Molecule, RecordError>> = crate::read_sdfile(sd_file);
+    let mol_list: Vec<Molecule> = mol_res_list.into_iter().filter_map(|m| m.ok()).collect();
+    mol_list
 }
@@ -0,0 +1,238 @@
+//! Multi-threaded parsing of SD and SMILES files
+//!
+//! One thread reads the input and splits it into chunks of records, a pool of workers parses them and the
+//! records are returned in input order, the same as from the sequential readers. The number of chunks in
+//! flight is limited, so the memory use does not grow with the file size.
+//!
+//! ```no_run
+//! use rdkitcffi::smiles::SmilesReader;
+//! use rdkitcffi::SDIterator;
+//!
+//! for record in SDIterator::new("library.sdf.gz").parallel(8, 1000) {
+//!     println!("{} {:?}", record.title, record.error);
+//! }
+//!
+//! let names: Vec<String> = SmilesReader::new("data/ringtest.smi")
+//!     .parallel(0, 100)
+//!     .filter(|r| r.mol.is_some())
+//!     .map(|r| r.name)
+//!     .collect();
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::panic;
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::vec;
+
+use crate::sdf::{SDIterator, SDRecord};
+use crate::smiles::{SmilesReader, SmilesRecord};
+
+/// Chunks in flight per worker, read ahead or waiting to be consumed
+const CHUNKS_PER_WORKER: usize = 2;
+
+/// A parsed record on its way to the consuming thread. Molecules are not `Send` because of their raw
+/// pointers, but the pickle is a plain buffer owned by the molecule and freed by RDKit, it is not tied to the
+/// thread which created it.
+struct Parsed<T>(T);
+
+unsafe impl Send for Parsed<SDRecord> {}
+unsafe impl Send for Parsed<SmilesRecord> {}
+
+/// Iterates over records parsed in a thread pool, in the order of the input
+pub struct ParallelReader<T> {
+    receive: Box<dyn FnMut() -> Option<(usize, Vec<T>)>>,
+    release: Box<dyn FnMut()>,
+    pending: BTreeMap<usize, Vec<T>>,
+    current: vec::IntoIter<T>,
+    next_chunk: usize,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<T> ParallelReader<T> {
+    /// Starts the reader thread and the workers.
+    /// `source` returns the unparsed records, `parse` runs in the workers.
+    fn spawn<J, S, P>(
+        mut source: S,
+        num_threads: usize,
+        chunk_size: usize,
+        parse: P,
+    ) -> ParallelReader<T>
+    where
+        T: 'static,
+        Parsed<T>: Send,
+        J: Send + 'static,
+        S: FnMut() -> Option<J> + Send + 'static,
+        P: Fn(J) -> T + Send + Sync + 'static,
+    {
+        let num_threads = if num_threads == 0 {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            num_threads
+        };
+        let chunk_size = chunk_size.max(1);
+        let capacity = num_threads * CHUNKS_PER_WORKER;
+        let (job_tx, job_rx) = sync_channel::<(usize, Vec<J>)>(capacity);
+        let (result_tx, result_rx) =
+            sync_channel::<(usize, thread::Result<Vec<Parsed<T>>>)>(capacity);
+        // a chunk is only read after the consumer released one, this bounds the chunks in memory
+        let (token_tx, token_rx) = sync_channel::<()>(capacity);
+        for _ in 0..capacity {
+            token_tx.send(()).unwrap();
+        }
+
+        let mut threads: Vec<JoinHandle<()>> = Vec::with_capacity(num_threads + 1);
+        threads.push(thread::spawn(move || {
+            let mut chunk_index = 0;
+            while token_rx.recv().is_ok() {
+                let chunk: Vec<J> = std::iter::from_fn(&mut source).take(chunk_size).collect();
+                let last = chunk.len() < chunk_size;
+                if chunk.is_empty() || job_tx.send((chunk_index, chunk)).is_err() || last {
+                    break;
+                }
+                chunk_index += 1;
+            }
+        }));
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let parse = Arc::new(parse);
+        for _ in 0..num_threads {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let parse = Arc::clone(&parse);
+            threads.push(thread::spawn(move || loop {
+                let job = job_rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let (chunk_index, chunk) = match job {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+                // a panic is handed to the consumer, which would otherwise wait for the chunk forever
+                let parsed = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    chunk
+                        .into_iter()
+                        .map(|raw| Parsed(parse(raw)))
+                        .collect::<Vec<_>>()
+                }));
+                let failed = parsed.is_err();
+                if result_tx.send((chunk_index, parsed)).is_err() || failed {
+                    break;
+                }
+            }));
+        }
+
+        ParallelReader {
+            receive: Box::new(move || {
+                let (chunk_index, chunk) = result_rx.recv().ok()?;
+                match chunk {
+                    Ok(chunk) => Some((chunk_index, chunk.into_iter().map(|p| p.0).collect())),
+                    Err(e) => panic::resume_unwind(e),
+                }
+            }),
+            release: Box::new(move || {
+                let _ = token_tx.try_send(());
+            }),
+            pending: BTreeMap::new(),
+            current: Vec::new().into_iter(),
+            next_chunk: 0,
+            threads,
+        }
+    }
+
+    /// Waits for the threads once all results were received, a panic of the reader thread is passed on
+    fn finish(&mut self) {
+        for handle in self.threads.drain(..) {
+            if let Err(e) = handle.join() {
+                panic::resume_unwind(e);
+            }
+        }
+    }
+}
+
+impl<T> Iterator for ParallelReader<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(record) = self.current.next() {
+                return Some(record);
+            }
+            if let Some(chunk) = self.pending.remove(&self.next_chunk) {
+                self.current = chunk.into_iter();
+                self.next_chunk += 1;
+                (self.release)();
+                continue;
+            }
+            match (self.receive)() {
+                Some((chunk_index, chunk)) => {
+                    self.pending.insert(chunk_index, chunk);
+                }
+                None => {
+                    self.finish();
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead + Send + 'static> SDIterator<R> {
+    /// Parses the remaining records in num_threads workers (0 uses all cores), chunk_size records at a time
+    pub fn parallel(mut self, num_threads: usize, chunk_size: usize) -> ParallelReader<SDRecord> {
+        ParallelReader::spawn(
+            move || self.next_raw(),
+            num_threads,
+            chunk_size,
+            |(index, line, block): (usize, usize, io::Result<String>)| {
+                SDRecord::from_raw(index, line, block)
+            },
+        )
+    }
+}
+
+impl<R: BufRead + Send + 'static> SmilesReader<R> {
+    /// Parses the remaining records in num_threads workers (0 uses all cores), chunk_size records at a time
+    pub fn parallel(
+        mut self,
+        num_threads: usize,
+        chunk_size: usize,
+    ) -> ParallelReader<SmilesRecord> {
+        self.read_title();
+        let parser = self.parser().clone();
+        ParallelReader::spawn(
+            move || self.next_raw(),
+            num_threads,
+            chunk_size,
+            move |(index, line_number, line): (usize, usize, io::Result<String>)| {
+                parser.parse_raw(line, index, line_number)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "broken worker")]
+    fn worker_panic() {
+        let parser = SmilesReader::from_reader(Cursor::new(Vec::new()))
+            .parser()
+            .clone();
+        let mut numbers = 0..100;
+        let reader = ParallelReader::spawn(
+            move || numbers.next(),
+            2,
+            5,
+            move |i: usize| {
+                if i == 42 {
+                    panic!("broken worker");
+                }
+                parser.parse_raw(Ok("C".to_string()), i, i + 1)
+            },
+        );
+        assert_eq!(reader.count(), 100);
+    }
+}
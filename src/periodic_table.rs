@@ -0,0 +1,373 @@
+//! Element data: symbols, names, masses, isotopes, common valences and covalent radii
+//!
+//! Average masses are the IUPAC standard atomic weights, for elements without stable isotopes the mass number
+//! of the longest lived isotope is used. Isotope masses and abundances follow NIST, covalent radii (in Å) are
+//! the single bond radii of Cordero et al. (2008), sp3 for carbon and low spin for Mn, Fe and Co.
+//!
+//! ```
+//! use rdkitcffi::periodic_table;
+//!
+//! let chlorine = periodic_table::from_symbol("Cl").unwrap();
+//! assert_eq!(chlorine.z, 17);
+//! assert_eq!(periodic_table::isotopes(17).len(), 3);
+//! println!("{} {:.4}", chlorine.name, periodic_table::monoisotopic_mass(17).unwrap());
+//! ```
+
+/// Properties of an element, z = 0 is the RDKit dummy atom `*`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElementData {
+    pub z: u8,
+    pub symbol: &'static str,
+    pub name: &'static str,
+    /// Standard atomic weight in u
+    pub average_mass: f64,
+    /// Common valences, ascending, empty if there is no default valence
+    pub valences: &'static [i32],
+    /// Single bond covalent radius in Å
+    pub covalent_radius: Option<f64>,
+}
+
+/// An isotope with its mass in u and its natural abundance as a fraction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Isotope {
+    pub z: u8,
+    pub mass_number: u16,
+    pub mass: f64,
+    /// 0 for radioisotopes
+    pub abundance: f64,
+}
+
+const fn el(
+    z: u8,
+    symbol: &'static str,
+    name: &'static str,
+    average_mass: f64,
+    valences: &'static [i32],
+    radius: f64,
+) -> ElementData {
+    ElementData {
+        z,
+        symbol,
+        name,
+        average_mass,
+        valences,
+        covalent_radius: if radius > 0.0 { Some(radius) } else { None },
+    }
+}
+
+const fn iso(z: u8, mass_number: u16, mass: f64, abundance: f64) -> Isotope {
+    Isotope {
+        z,
+        mass_number,
+        mass,
+        abundance,
+    }
+}
+
+#[rustfmt::skip]
+pub(crate) static ELEMENT_DATA: [ElementData; 119] = [
+    el(0, "*", "Dummy", 0.0, &[], 0.0),
+    el(1, "H", "Hydrogen", 1.008, &[1], 0.31),
+    el(2, "He", "Helium", 4.002602, &[0], 0.28),
+    el(3, "Li", "Lithium", 6.94, &[1], 1.28),
+    el(4, "Be", "Beryllium", 9.0121831, &[2], 0.96),
+    el(5, "B", "Boron", 10.81, &[3], 0.84),
+    el(6, "C", "Carbon", 12.011, &[4], 0.76),
+    el(7, "N", "Nitrogen", 14.007, &[3], 0.71),
+    el(8, "O", "Oxygen", 15.999, &[2], 0.66),
+    el(9, "F", "Fluorine", 18.998403163, &[1], 0.57),
+    el(10, "Ne", "Neon", 20.1797, &[0], 0.58),
+    el(11, "Na", "Sodium", 22.98976928, &[1], 1.66),
+    el(12, "Mg", "Magnesium", 24.305, &[2], 1.41),
+    el(13, "Al", "Aluminium", 26.9815385, &[3], 1.21),
+    el(14, "Si", "Silicon", 28.085, &[4], 1.11),
+    el(15, "P", "Phosphorus", 30.973761998, &[3, 5], 1.07),
+    el(16, "S", "Sulfur", 32.06, &[2, 4, 6], 1.05),
+    el(17, "Cl", "Chlorine", 35.45, &[1, 3, 5, 7], 1.02),
+    el(18, "Ar", "Argon", 39.948, &[0], 1.06),
+    el(19, "K", "Potassium", 39.0983, &[1], 2.03),
+    el(20, "Ca", "Calcium", 40.078, &[2], 1.76),
+    el(21, "Sc", "Scandium", 44.955908, &[], 1.70),
+    el(22, "Ti", "Titanium", 47.867, &[], 1.60),
+    el(23, "V", "Vanadium", 50.9415, &[], 1.53),
+    el(24, "Cr", "Chromium", 51.9961, &[], 1.39),
+    el(25, "Mn", "Manganese", 54.938044, &[], 1.39),
+    el(26, "Fe", "Iron", 55.845, &[], 1.32),
+    el(27, "Co", "Cobalt", 58.933194, &[], 1.26),
+    el(28, "Ni", "Nickel", 58.6934, &[], 1.24),
+    el(29, "Cu", "Copper", 63.546, &[], 1.32),
+    el(30, "Zn", "Zinc", 65.38, &[2], 1.22),
+    el(31, "Ga", "Gallium", 69.723, &[3], 1.22),
+    el(32, "Ge", "Germanium", 72.630, &[4], 1.20),
+    el(33, "As", "Arsenic", 74.921595, &[3, 5], 1.19),
+    el(34, "Se", "Selenium", 78.971, &[2, 4, 6], 1.20),
+    el(35, "Br", "Bromine", 79.904, &[1, 3, 5, 7], 1.20),
+    el(36, "Kr", "Krypton", 83.798, &[0], 1.16),
+    el(37, "Rb", "Rubidium", 85.4678, &[1], 2.20),
+    el(38, "Sr", "Strontium", 87.62, &[2], 1.95),
+    el(39, "Y", "Yttrium", 88.90584, &[], 1.90),
+    el(40, "Zr", "Zirconium", 91.224, &[], 1.75),
+    el(41, "Nb", "Niobium", 92.90637, &[], 1.64),
+    el(42, "Mo", "Molybdenum", 95.95, &[], 1.54),
+    el(43, "Tc", "Technetium", 98.0, &[], 1.47),
+    el(44, "Ru", "Ruthenium", 101.07, &[], 1.46),
+    el(45, "Rh", "Rhodium", 102.90550, &[], 1.42),
+    el(46, "Pd", "Palladium", 106.42, &[], 1.39),
+    el(47, "Ag", "Silver", 107.8682, &[], 1.45),
+    el(48, "Cd", "Cadmium", 112.414, &[2], 1.44),
+    el(49, "In", "Indium", 114.818, &[3], 1.42),
+    el(50, "Sn", "Tin", 118.710, &[2, 4], 1.39),
+    el(51, "Sb", "Antimony", 121.760, &[3, 5], 1.39),
+    el(52, "Te", "Tellurium", 127.60, &[2, 4, 6], 1.38),
+    el(53, "I", "Iodine", 126.90447, &[1, 3, 5, 7], 1.39),
+    el(54, "Xe", "Xenon", 131.293, &[0, 2, 4, 6], 1.40),
+    el(55, "Cs", "Caesium", 132.90545196, &[1], 2.44),
+    el(56, "Ba", "Barium", 137.327, &[2], 2.15),
+    el(57, "La", "Lanthanum", 138.90547, &[], 2.07),
+    el(58, "Ce", "Cerium", 140.116, &[], 2.04),
+    el(59, "Pr", "Praseodymium", 140.90766, &[], 2.03),
+    el(60, "Nd", "Neodymium", 144.242, &[], 2.01),
+    el(61, "Pm", "Promethium", 145.0, &[], 1.99),
+    el(62, "Sm", "Samarium", 150.36, &[], 1.98),
+    el(63, "Eu", "Europium", 151.964, &[], 1.98),
+    el(64, "Gd", "Gadolinium", 157.25, &[], 1.96),
+    el(65, "Tb", "Terbium", 158.92535, &[], 1.94),
+    el(66, "Dy", "Dysprosium", 162.500, &[], 1.92),
+    el(67, "Ho", "Holmium", 164.93033, &[], 1.92),
+    el(68, "Er", "Erbium", 167.259, &[], 1.89),
+    el(69, "Tm", "Thulium", 168.93422, &[], 1.90),
+    el(70, "Yb", "Ytterbium", 173.045, &[], 1.87),
+    el(71, "Lu", "Lutetium", 174.9668, &[], 1.87),
+    el(72, "Hf", "Hafnium", 178.49, &[], 1.75),
+    el(73, "Ta", "Tantalum", 180.94788, &[], 1.70),
+    el(74, "W", "Tungsten", 183.84, &[], 1.62),
+    el(75, "Re", "Rhenium", 186.207, &[], 1.51),
+    el(76, "Os", "Osmium", 190.23, &[], 1.44),
+    el(77, "Ir", "Iridium", 192.217, &[], 1.41),
+    el(78, "Pt", "Platinum", 195.084, &[], 1.36),
+    el(79, "Au", "Gold", 196.966569, &[], 1.36),
+    el(80, "Hg", "Mercury", 200.592, &[2], 1.32),
+    el(81, "Tl", "Thallium", 204.38, &[1, 3], 1.45),
+    el(82, "Pb", "Lead", 207.2, &[2, 4], 1.46),
+    el(83, "Bi", "Bismuth", 208.98040, &[3, 5], 1.48),
+    el(84, "Po", "Polonium", 209.0, &[2, 4], 1.40),
+    el(85, "At", "Astatine", 210.0, &[1], 1.50),
+    el(86, "Rn", "Radon", 222.0, &[0], 1.50),
+    el(87, "Fr", "Francium", 223.0, &[1], 2.60),
+    el(88, "Ra", "Radium", 226.0, &[2], 2.21),
+    el(89, "Ac", "Actinium", 227.0, &[], 2.15),
+    el(90, "Th", "Thorium", 232.0377, &[], 2.06),
+    el(91, "Pa", "Protactinium", 231.03588, &[], 2.00),
+    el(92, "U", "Uranium", 238.02891, &[], 1.96),
+    el(93, "Np", "Neptunium", 237.0, &[], 1.90),
+    el(94, "Pu", "Plutonium", 244.0, &[], 1.87),
+    el(95, "Am", "Americium", 243.0, &[], 1.80),
+    el(96, "Cm", "Curium", 247.0, &[], 1.69),
+    el(97, "Bk", "Berkelium", 247.0, &[], 0.0),
+    el(98, "Cf", "Californium", 251.0, &[], 0.0),
+    el(99, "Es", "Einsteinium", 252.0, &[], 0.0),
+    el(100, "Fm", "Fermium", 257.0, &[], 0.0),
+    el(101, "Md", "Mendelevium", 258.0, &[], 0.0),
+    el(102, "No", "Nobelium", 259.0, &[], 0.0),
+    el(103, "Lr", "Lawrencium", 266.0, &[], 0.0),
+    el(104, "Rf", "Rutherfordium", 267.0, &[], 0.0),
+    el(105, "Db", "Dubnium", 268.0, &[], 0.0),
+    el(106, "Sg", "Seaborgium", 269.0, &[], 0.0),
+    el(107, "Bh", "Bohrium", 270.0, &[], 0.0),
+    el(108, "Hs", "Hassium", 269.0, &[], 0.0),
+    el(109, "Mt", "Meitnerium", 278.0, &[], 0.0),
+    el(110, "Ds", "Darmstadtium", 281.0, &[], 0.0),
+    el(111, "Rg", "Roentgenium", 282.0, &[], 0.0),
+    el(112, "Cn", "Copernicium", 285.0, &[], 0.0),
+    el(113, "Nh", "Nihonium", 286.0, &[], 0.0),
+    el(114, "Fl", "Flerovium", 289.0, &[], 0.0),
+    el(115, "Mc", "Moscovium", 290.0, &[], 0.0),
+    el(116, "Lv", "Livermorium", 293.0, &[], 0.0),
+    el(117, "Ts", "Tennessine", 294.0, &[], 0.0),
+    el(118, "Og", "Oganesson", 294.0, &[], 0.0),
+];
+
+/// Naturally occurring isotopes and radioisotopes common in labelling, sorted by z and mass number
+#[rustfmt::skip]
+static ISOTOPES: &[Isotope] = &[
+    iso(1, 1, 1.00782503223, 0.999885), iso(1, 2, 2.01410177812, 0.000115), iso(1, 3, 3.01604928132, 0.0),
+    iso(2, 3, 3.0160293201, 0.00000134), iso(2, 4, 4.00260325413, 0.99999866),
+    iso(3, 6, 6.0151228874, 0.0759), iso(3, 7, 7.0160034366, 0.9241),
+    iso(4, 9, 9.012183065, 1.0),
+    iso(5, 10, 10.01293695, 0.199), iso(5, 11, 11.00930536, 0.801),
+    iso(6, 11, 11.0114336, 0.0), iso(6, 12, 12.0, 0.9893), iso(6, 13, 13.00335483507, 0.0107),
+    iso(6, 14, 14.0032419884, 0.0),
+    iso(7, 13, 13.00573861, 0.0), iso(7, 14, 14.00307400443, 0.99636), iso(7, 15, 15.00010889888, 0.00364),
+    iso(8, 15, 15.0030656, 0.0), iso(8, 16, 15.99491461957, 0.99757), iso(8, 17, 16.99913175650, 0.00038),
+    iso(8, 18, 17.99915961286, 0.00205),
+    iso(9, 18, 18.0009380, 0.0), iso(9, 19, 18.99840316273, 1.0),
+    iso(10, 20, 19.9924401762, 0.9048), iso(10, 21, 20.993846685, 0.0027), iso(10, 22, 21.991385114, 0.0925),
+    iso(11, 23, 22.9897692820, 1.0),
+    iso(12, 24, 23.985041697, 0.7899), iso(12, 25, 24.985836976, 0.1000), iso(12, 26, 25.982592968, 0.1101),
+    iso(13, 27, 26.98153853, 1.0),
+    iso(14, 28, 27.97692653465, 0.92223), iso(14, 29, 28.97649466490, 0.04685), iso(14, 30, 29.973770136, 0.03092),
+    iso(15, 31, 30.97376199842, 1.0), iso(15, 32, 31.97390764, 0.0),
+    iso(16, 32, 31.9720711744, 0.9499), iso(16, 33, 32.9714589098, 0.0075), iso(16, 34, 33.967867004, 0.0425),
+    iso(16, 35, 34.96903231, 0.0), iso(16, 36, 35.96708071, 0.0001),
+    iso(17, 35, 34.968852682, 0.7576), iso(17, 36, 35.96830682, 0.0), iso(17, 37, 36.965902602, 0.2424),
+    iso(18, 36, 35.967545105, 0.003336), iso(18, 38, 37.96273211, 0.000629), iso(18, 40, 39.9623831237, 0.996035),
+    iso(19, 39, 38.9637064864, 0.932581), iso(19, 40, 39.963998166, 0.000117), iso(19, 41, 40.9618252579, 0.067302),
+    iso(20, 40, 39.962590863, 0.96941), iso(20, 42, 41.95861783, 0.00647), iso(20, 43, 42.95876644, 0.00135),
+    iso(20, 44, 43.9554816, 0.02086), iso(20, 46, 45.953689, 0.00004), iso(20, 48, 47.95252276, 0.00187),
+    iso(21, 45, 44.95590828, 1.0),
+    iso(22, 46, 45.95262772, 0.0825), iso(22, 47, 46.95175879, 0.0744), iso(22, 48, 47.94794198, 0.7372),
+    iso(22, 49, 48.94786568, 0.0541), iso(22, 50, 49.94478689, 0.0518),
+    iso(23, 50, 49.94715601, 0.00250), iso(23, 51, 50.94395704, 0.99750),
+    iso(24, 50, 49.94604183, 0.04345), iso(24, 52, 51.94050623, 0.83789), iso(24, 53, 52.94064815, 0.09501),
+    iso(24, 54, 53.93887916, 0.02365),
+    iso(25, 55, 54.93804391, 1.0),
+    iso(26, 54, 53.93960899, 0.05845), iso(26, 56, 55.93493633, 0.91754), iso(26, 57, 56.93539284, 0.02119),
+    iso(26, 58, 57.93327443, 0.00282),
+    iso(27, 59, 58.93319429, 1.0),
+    iso(28, 58, 57.93534241, 0.68077), iso(28, 60, 59.93078588, 0.26223), iso(28, 61, 60.93105557, 0.011399),
+    iso(28, 62, 61.92834537, 0.036346), iso(28, 64, 63.92796682, 0.009255),
+    iso(29, 63, 62.92959772, 0.6915), iso(29, 65, 64.92778970, 0.3085),
+    iso(30, 64, 63.92914201, 0.4917), iso(30, 66, 65.92603381, 0.2773), iso(30, 67, 66.92712775, 0.0404),
+    iso(30, 68, 67.92484455, 0.1845), iso(30, 70, 69.9253192, 0.0061),
+    iso(31, 69, 68.9255735, 0.60108), iso(31, 71, 70.92470258, 0.39892),
+    iso(32, 70, 69.92424875, 0.2057), iso(32, 72, 71.922075826, 0.2745), iso(32, 73, 72.923458956, 0.0775),
+    iso(32, 74, 73.921177761, 0.3650), iso(32, 76, 75.921402726, 0.0773),
+    iso(33, 75, 74.92159457, 1.0),
+    iso(34, 74, 73.922475934, 0.0089), iso(34, 76, 75.919213704, 0.0937), iso(34, 77, 76.919914154, 0.0763),
+    iso(34, 78, 77.91730928, 0.2377), iso(34, 80, 79.9165218, 0.4961), iso(34, 82, 81.9166995, 0.0873),
+    iso(35, 79, 78.9183376, 0.5069), iso(35, 81, 80.9162897, 0.4931),
+    iso(36, 78, 77.92036494, 0.00355), iso(36, 80, 79.91637808, 0.02286), iso(36, 82, 81.91348273, 0.11593),
+    iso(36, 83, 82.91412716, 0.11500), iso(36, 84, 83.9114977282, 0.56987), iso(36, 86, 85.9106106269, 0.17279),
+    iso(37, 85, 84.9117897379, 0.7217), iso(37, 87, 86.9091805310, 0.2783),
+    iso(38, 84, 83.9134191, 0.0056), iso(38, 86, 85.9092606, 0.0986), iso(38, 87, 86.9088775, 0.0700),
+    iso(38, 88, 87.9056125, 0.8258),
+    iso(39, 89, 88.9058403, 1.0),
+    iso(40, 90, 89.9046977, 0.5145), iso(40, 91, 90.9056396, 0.1122), iso(40, 92, 91.9050347, 0.1715),
+    iso(40, 94, 93.9063108, 0.1738), iso(40, 96, 95.9082714, 0.0280),
+    iso(41, 93, 92.9063730, 1.0),
+    iso(42, 92, 91.90680796, 0.1453), iso(42, 94, 93.90508490, 0.0915), iso(42, 95, 94.90583877, 0.1584),
+    iso(42, 96, 95.90467612, 0.1667), iso(42, 97, 96.90601812, 0.0960), iso(42, 98, 97.90540482, 0.2439),
+    iso(42, 100, 99.9074718, 0.0982),
+    iso(43, 99, 98.9062508, 0.0),
+    iso(44, 96, 95.90759025, 0.0554), iso(44, 98, 97.9052868, 0.0187), iso(44, 99, 98.9059341, 0.1276),
+    iso(44, 100, 99.9042143, 0.1260), iso(44, 101, 100.9055769, 0.1706), iso(44, 102, 101.9043441, 0.3155),
+    iso(44, 104, 103.9054275, 0.1862),
+    iso(45, 103, 102.9054980, 1.0),
+    iso(46, 102, 101.9056022, 0.0102), iso(46, 104, 103.9040305, 0.1114), iso(46, 105, 104.9050796, 0.2233),
+    iso(46, 106, 105.9034804, 0.2733), iso(46, 108, 107.9038916, 0.2646), iso(46, 110, 109.9051722, 0.1172),
+    iso(47, 107, 106.9050916, 0.51839), iso(47, 109, 108.9047553, 0.48161),
+    iso(48, 106, 105.9064599, 0.0125), iso(48, 108, 107.9041834, 0.0089), iso(48, 110, 109.90300661, 0.1249),
+    iso(48, 111, 110.90418287, 0.1280), iso(48, 112, 111.90276287, 0.2413), iso(48, 113, 112.90440813, 0.1222),
+    iso(48, 114, 113.90336509, 0.2873), iso(48, 116, 115.90476315, 0.0749),
+    iso(49, 113, 112.90406184, 0.0429), iso(49, 115, 114.903878776, 0.9571),
+    iso(50, 112, 111.90482387, 0.0097), iso(50, 114, 113.9027827, 0.0066), iso(50, 115, 114.903344699, 0.0034),
+    iso(50, 116, 115.90174280, 0.1454), iso(50, 117, 116.90295398, 0.0768), iso(50, 118, 117.90160657, 0.2422),
+    iso(50, 119, 118.90331117, 0.0859), iso(50, 120, 119.90220163, 0.3258), iso(50, 122, 121.9034438, 0.0463),
+    iso(50, 124, 123.9052766, 0.0579),
+    iso(51, 121, 120.9038120, 0.5721), iso(51, 123, 122.9042132, 0.4279),
+    iso(52, 120, 119.9040593, 0.0009), iso(52, 122, 121.9030435, 0.0255), iso(52, 123, 122.9042698, 0.0089),
+    iso(52, 124, 123.9028171, 0.0474), iso(52, 125, 124.9044299, 0.0707), iso(52, 126, 125.9033109, 0.1884),
+    iso(52, 128, 127.90446128, 0.3174), iso(52, 130, 129.906222748, 0.3408),
+    iso(53, 123, 122.9055898, 0.0), iso(53, 124, 123.9062099, 0.0), iso(53, 125, 124.9046294, 0.0),
+    iso(53, 127, 126.9044719, 1.0), iso(53, 131, 130.9061263, 0.0),
+    iso(54, 124, 123.9058920, 0.000952), iso(54, 126, 125.9042983, 0.000890), iso(54, 128, 127.9035310, 0.019102),
+    iso(54, 129, 128.9047808611, 0.264006), iso(54, 130, 129.903509349, 0.040710), iso(54, 131, 130.90508406, 0.212324),
+    iso(54, 132, 131.9041550856, 0.269086), iso(54, 134, 133.90539466, 0.104357), iso(54, 136, 135.907214484, 0.088573),
+    iso(55, 133, 132.9054519610, 1.0),
+    iso(56, 130, 129.9063207, 0.00106), iso(56, 132, 131.9050611, 0.00101), iso(56, 134, 133.90450818, 0.02417),
+    iso(56, 135, 134.90568838, 0.06592), iso(56, 136, 135.90457573, 0.07854), iso(56, 137, 136.90582714, 0.11232),
+    iso(56, 138, 137.90524700, 0.71698),
+    iso(57, 138, 137.9071149, 0.0008881), iso(57, 139, 138.9063563, 0.9991119),
+    iso(58, 136, 135.90712921, 0.00185), iso(58, 138, 137.905991, 0.00251), iso(58, 140, 139.9054431, 0.88450),
+    iso(58, 142, 141.9092504, 0.11114),
+    iso(59, 141, 140.9076576, 1.0),
+    iso(60, 142, 141.9077290, 0.27152), iso(60, 143, 142.9098200, 0.12174), iso(60, 144, 143.9100930, 0.23798),
+    iso(60, 145, 144.9125793, 0.08293), iso(60, 146, 145.9131226, 0.17189), iso(60, 148, 147.9168993, 0.05756),
+    iso(60, 150, 149.9209022, 0.05638),
+    iso(62, 144, 143.9120065, 0.0307), iso(62, 147, 146.9149044, 0.1499), iso(62, 148, 147.9148292, 0.1124),
+    iso(62, 149, 148.9171921, 0.1382), iso(62, 150, 149.9172829, 0.0738), iso(62, 152, 151.9197397, 0.2675),
+    iso(62, 154, 153.9222169, 0.2275),
+    iso(63, 151, 150.9198578, 0.4781), iso(63, 153, 152.9212380, 0.5219),
+    iso(64, 152, 151.9197995, 0.0020), iso(64, 154, 153.9208741, 0.0218), iso(64, 155, 154.9226305, 0.1480),
+    iso(64, 156, 155.9221312, 0.2047), iso(64, 157, 156.9239686, 0.1565), iso(64, 158, 157.9241123, 0.2484),
+    iso(64, 160, 159.9270624, 0.2186),
+    iso(65, 159, 158.9253547, 1.0),
+    iso(66, 156, 155.9242847, 0.00056), iso(66, 158, 157.9244159, 0.00095), iso(66, 160, 159.9252046, 0.02329),
+    iso(66, 161, 160.9269405, 0.18889), iso(66, 162, 161.9268056, 0.25475), iso(66, 163, 162.9287383, 0.24896),
+    iso(66, 164, 163.9291819, 0.28260),
+    iso(67, 165, 164.9303288, 1.0),
+    iso(68, 162, 161.9287884, 0.00139), iso(68, 164, 163.9292088, 0.01601), iso(68, 166, 165.9302995, 0.33503),
+    iso(68, 167, 166.9320546, 0.22869), iso(68, 168, 167.9323767, 0.26978), iso(68, 170, 169.9354702, 0.14910),
+    iso(69, 169, 168.9342179, 1.0),
+    iso(70, 168, 167.9338896, 0.00123), iso(70, 170, 169.9347664, 0.02982), iso(70, 171, 170.9363302, 0.1409),
+    iso(70, 172, 171.9363859, 0.2168), iso(70, 173, 172.9382151, 0.16103), iso(70, 174, 173.9388664, 0.32026),
+    iso(70, 176, 175.9425764, 0.12996),
+    iso(71, 175, 174.9407752, 0.97401), iso(71, 176, 175.9426897, 0.02599),
+    iso(72, 174, 173.9400461, 0.0016), iso(72, 176, 175.9414076, 0.0526), iso(72, 177, 176.9432277, 0.1860),
+    iso(72, 178, 177.9437058, 0.2728), iso(72, 179, 178.9458232, 0.1362), iso(72, 180, 179.9465570, 0.3508),
+    iso(73, 180, 179.9474648, 0.0001201), iso(73, 181, 180.9479958, 0.9998799),
+    iso(74, 180, 179.9467108, 0.0012), iso(74, 182, 181.94820394, 0.2650), iso(74, 183, 182.95022275, 0.1431),
+    iso(74, 184, 183.95093092, 0.3064), iso(74, 186, 185.9543628, 0.2843),
+    iso(75, 185, 184.9529545, 0.3740), iso(75, 187, 186.9557501, 0.6260),
+    iso(76, 184, 183.9524885, 0.0002), iso(76, 186, 185.9538350, 0.0159), iso(76, 187, 186.9557474, 0.0196),
+    iso(76, 188, 187.9558352, 0.1324), iso(76, 189, 188.9581442, 0.1615), iso(76, 190, 189.9584437, 0.2626),
+    iso(76, 192, 191.9614770, 0.4078),
+    iso(77, 191, 190.9605893, 0.373), iso(77, 193, 192.9629216, 0.627),
+    iso(78, 190, 189.9599297, 0.00012), iso(78, 192, 191.9610387, 0.00782), iso(78, 194, 193.9626809, 0.3286),
+    iso(78, 195, 194.9647917, 0.3378), iso(78, 196, 195.96495209, 0.2521), iso(78, 198, 197.9678949, 0.07356),
+    iso(79, 197, 196.96656879, 1.0),
+    iso(80, 196, 195.9658326, 0.0015), iso(80, 198, 197.96676860, 0.0997), iso(80, 199, 198.96828064, 0.1687),
+    iso(80, 200, 199.96832659, 0.2310), iso(80, 201, 200.97030284, 0.1318), iso(80, 202, 201.97064340, 0.2986),
+    iso(80, 204, 203.97349398, 0.0687),
+    iso(81, 203, 202.9723446, 0.2952), iso(81, 205, 204.9744278, 0.7048),
+    iso(82, 204, 203.9730440, 0.014), iso(82, 206, 205.9744657, 0.241), iso(82, 207, 206.9758973, 0.221),
+    iso(82, 208, 207.9766525, 0.524),
+    iso(83, 209, 208.9803991, 1.0),
+    iso(90, 232, 232.0380558, 1.0),
+    iso(91, 231, 231.0358842, 1.0),
+    iso(92, 234, 234.0409523, 0.000054), iso(92, 235, 235.0439301, 0.007204), iso(92, 238, 238.0507884, 0.992742),
+];
+
+/// The element with atomic number z, None outside of 0..=118
+pub fn element(z: i32) -> Option<&'static ElementData> {
+    if z < 0 {
+        return None;
+    }
+    ELEMENT_DATA.get(z as usize)
+}
+
+/// Looks up an element by its case sensitive symbol, `*` is the dummy atom
+pub fn from_symbol(symbol: &str) -> Option<&'static ElementData> {
+    ELEMENT_DATA.iter().find(|e| e.symbol == symbol)
+}
+
+pub fn symbol(z: i32) -> Option<&'static str> {
+    element(z).map(|e| e.symbol)
+}
+
+/// Known isotopes of an element sorted by mass number, natural ones have an abundance above 0
+pub fn isotopes(z: i32) -> &'static [Isotope] {
+    if !(0..=118).contains(&z) {
+        return &[];
+    }
+    let start = ISOTOPES.partition_point(|i| (i.z as i32) < z);
+    let end = ISOTOPES.partition_point(|i| (i.z as i32) <= z);
+    &ISOTOPES[start..end]
+}
+
+pub fn isotope(z: i32, mass_number: u16) -> Option<&'static Isotope> {
+    isotopes(z).iter().find(|i| i.mass_number == mass_number)
+}
+
+/// The most abundant isotope, None for elements without stable isotopes
+pub fn most_abundant_isotope(z: i32) -> Option<&'static Isotope> {
+    isotopes(z)
+        .iter()
+        .filter(|i| i.abundance > 0.0)
+        .max_by(|a, b| a.abundance.partial_cmp(&b.abundance).unwrap())
+}
+
+/// Mass of the most abundant isotope
+pub fn monoisotopic_mass(z: i32) -> Option<f64> {
+    most_abundant_isotope(z).map(|i| i.mass)
+}
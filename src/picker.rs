@@ -0,0 +1,154 @@
+//! MaxMin diversity picking on fingerprints
+//!
+//! Distances are computed lazily, only the minimum distance of each candidate to the picked set is kept,
+//! so the memory requirement is linear in the number of fingerprints.
+//!
+//! ```no_run
+//! use rdkitcffi::fingerprint::FingerprintKind;
+//! use rdkitcffi::picker::{MaxMinPicker, Seed};
+//!
+//! let options = r#"{"radius":2,"nBits":2048}"#;
+//! let catalogue = rdkitcffi::read_smifile_unwrap("data/ringtest.smi");
+//! let owned = rdkitcffi::read_smifile_unwrap("data/test.smi");
+//! let fps = FingerprintKind::Morgan.compute_all(&catalogue, options);
+//! let owned_fps = FingerprintKind::Morgan.compute_all(&owned, options);
+//!
+//! let picks = MaxMinPicker::new(&fps)
+//!     .with_owned(&owned_fps)
+//!     .with_seed(Seed::Random(42))
+//!     .pick(5);
+//! ```
+
+use crate::fingerprint;
+
+/// How the first fingerprint is chosen if there is no owned set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Seed {
+    /// Start with the fingerprint at this index, nothing is picked if it is out of range
+    Index(usize),
+    /// Start with a random fingerprint, the value seeds the random number generator
+    Random(u64),
+}
+
+pub struct MaxMinPicker<'a> {
+    fps: &'a [Vec<u8>],
+    owned: &'a [Vec<u8>],
+    seed: Seed,
+    threshold: f32,
+}
+
+// splitmix64, good enough to choose the first pick without an additional dependency
+fn random_index(seed: u64, n: usize) -> usize {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z % n as u64) as usize
+}
+
+impl<'a> MaxMinPicker<'a> {
+    /// Creates a picker over a pool of fingerprints, starting with the first one
+    pub fn new(fps: &'a [Vec<u8>]) -> MaxMinPicker<'a> {
+        MaxMinPicker {
+            fps,
+            owned: &[],
+            seed: Seed::Index(0),
+            threshold: 0.0,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: Seed) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Already owned fingerprints, picks are chosen to be diverse from these as well. The seed is ignored.
+    pub fn with_owned(mut self, owned: &'a [Vec<u8>]) -> Self {
+        self.owned = owned;
+        self
+    }
+
+    /// Stop picking once the largest minimum Tanimoto distance drops below the threshold
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Picks up to n diverse fingerprints and returns their indices in the order picked
+    pub fn pick(&self, n: usize) -> Vec<usize> {
+        let n = n.min(self.fps.len());
+        let counts: Vec<u32> = self
+            .fps
+            .iter()
+            .map(|fp| fingerprint::popcount(fp))
+            .collect();
+        let distance = |i: usize, other: &[u8], other_count: u32| {
+            1.0 - fingerprint::tanimoto_with_counts(&self.fps[i], other, counts[i], other_count)
+        };
+
+        let mut picks: Vec<usize> = Vec::with_capacity(n);
+        if n == 0 {
+            return picks;
+        }
+        // minimum distance to the picked (and owned) set, None once picked
+        let mut min_dist: Vec<Option<f32>> = vec![Some(f32::MAX); self.fps.len()];
+        for owned in self.owned.iter() {
+            let owned_count = fingerprint::popcount(owned);
+            for (i, d) in min_dist.iter_mut().enumerate() {
+                let dist = distance(i, owned, owned_count);
+                *d = d.map(|d| d.min(dist));
+            }
+        }
+
+        let mut next = if self.owned.is_empty() {
+            match self.seed {
+                Seed::Index(i) if i < self.fps.len() => i,
+                Seed::Index(_) => return picks,
+                Seed::Random(seed) => random_index(seed, self.fps.len()),
+            }
+        } else {
+            match Self::most_distant(&min_dist, self.threshold) {
+                Some(i) => i,
+                None => return picks,
+            }
+        };
+
+        loop {
+            picks.push(next);
+            min_dist[next] = None;
+            if picks.len() == n {
+                break;
+            }
+            for (i, d) in min_dist.iter_mut().enumerate() {
+                if let Some(current) = d {
+                    let dist = distance(i, &self.fps[next], counts[next]);
+                    if dist < *current {
+                        *current = dist;
+                    }
+                }
+            }
+            next = match Self::most_distant(&min_dist, self.threshold) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+        picks
+    }
+
+    fn most_distant(min_dist: &[Option<f32>], threshold: f32) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, d) in min_dist.iter().enumerate() {
+            if let Some(d) = d {
+                if *d >= threshold && best.is_none_or(|(_, b)| *d > b) {
+                    best = Some((i, *d));
+                }
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
+
+/// Picks n diverse fingerprints starting with the first one
+pub fn maxmin_pick(fps: &[Vec<u8>], n: usize) -> Vec<usize> {
+    MaxMinPicker::new(fps).pick(n)
+}
@@ -0,0 +1,336 @@
+//! Streaming access to SD files
+//!
+//! Records are read line by line from any [`BufRead`] source, so large files, stdin or decompressed streams
+//! never have to be kept in memory. A `$$$$` line terminates a record, `$$$$` within data fields is kept.
+//!
+//! Each record carries the molecule, its title line and the SD data fields in file order. A molblock which
+//! RDKit rejects still gives a record with its title and data fields, the reason is kept in `error`. When
+//! the file stops being readable, e.g. a truncated `.sdf.gz`, the iteration ends with an extra record
+//! holding only the I/O error.
+//!
+//! ```no_run
+//! use rdkitcffi::SDIterator;
+//!
+//! let stdin = std::io::stdin();
+//! for record in SDIterator::from_reader(stdin.lock()) {
+//!     println!("{} {:?} {:?}", record.title, record.mol, record.props.get("ACTIVITY"));
+//! }
+//! ```
+//!
+//! Records can be written with an [`SDWriter`]:
+//!
+//! ```no_run
+//! use rdkitcffi::{SDIterator, SDWriter};
+//!
+//! let mut writer = SDWriter::create("neutralized.sdf").unwrap();
+//! for mut record in SDIterator::new("data/test.sdf") {
+//!     if let Some(mol) = record.mol.as_mut() {
+//!         mol.neutralize("");
+//!     }
+//!     record.props.insert("standardized".to_string(), "1".to_string());
+//!     writer.write_record(&record).unwrap();
+//! }
+//! writer.finish().unwrap();
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::compression::{self, CompressedWriter};
+use crate::error::{self, RecordError};
+use crate::Molecule;
+
+/// A single SD file entry
+#[derive(Debug)]
+pub struct SDRecord {
+    /// The molecule, None if it could not be parsed
+    pub mol: Option<Molecule>,
+    /// The first line of the molblock
+    pub title: String,
+    /// The data fields `> <TAG>` with their values
+    pub props: IndexMap<String, String>,
+    /// Position of the record in the file, empty blocks between `$$$$` lines are not counted
+    pub index: usize,
+    /// Line on which the record starts
+    pub line: usize,
+    /// Why the molecule could not be parsed, or why reading the input failed
+    pub error: Option<RecordError>,
+}
+
+impl SDRecord {
+    /// Parses the text of a record, i.e. the molblock followed by optional data fields
+    pub fn from_block(block: &str) -> SDRecord {
+        SDRecord::parse(block, 0, 1)
+    }
+
+    /// Parses a record at a position of the file, which is used for error reporting
+    pub(crate) fn parse(block: &str, index: usize, line: usize) -> SDRecord {
+        let (molblock, data) = split_molblock(block);
+        let title = molblock.lines().next().unwrap_or("").trim_end().to_string();
+        let (mol, error) = error::parse_record(molblock, index, line, &title);
+        SDRecord {
+            mol,
+            title,
+            props: parse_data_fields(data),
+            index,
+            line,
+            error,
+        }
+    }
+
+    /// Parses a block or turns the read error which ended the input into a record
+    pub(crate) fn from_raw(index: usize, line: usize, block: io::Result<String>) -> SDRecord {
+        match block {
+            Ok(block) => SDRecord::parse(&block, index, line),
+            Err(e) => SDRecord {
+                mol: None,
+                title: String::new(),
+                props: IndexMap::new(),
+                index,
+                line,
+                error: Some(error::read_error(index, line, &e)),
+            },
+        }
+    }
+
+    /// The molecule, or the error of a molblock RDKit rejected or of a file which could not be read
+    pub fn into_result(self) -> Result<Molecule, RecordError> {
+        error::record_result(self.mol, self.error, self.index, self.line, self.title)
+    }
+}
+
+/// Splits a record after the `M  END` line
+fn split_molblock(block: &str) -> (&str, &str) {
+    let mut pos = 0;
+    for line in block.split_inclusive('\n') {
+        pos += line.len();
+        if line.trim_end() == "M  END" {
+            return (&block[..pos], &block[pos..]);
+        }
+    }
+    (block, "")
+}
+
+/// Parses data items, the field name is taken from the angle brackets of the header line
+fn parse_data_fields(data: &str) -> IndexMap<String, String> {
+    let mut props: IndexMap<String, String> = IndexMap::new();
+    let mut lines = data.lines();
+    while let Some(line) = lines.next() {
+        let header = match line.strip_prefix('>') {
+            Some(header) => header,
+            None => continue,
+        };
+        let name = match (header.find('<'), header.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &header[start + 1..end],
+            _ => header.trim(),
+        };
+        let mut value: Vec<&str> = Vec::new();
+        for value_line in lines.by_ref() {
+            if value_line.trim_end().is_empty() {
+                break;
+            }
+            value.push(value_line);
+        }
+        props.insert(name.to_string(), value.join("\n"));
+    }
+    props
+}
+
+/// Splits an SD stream into the text blocks between `$$$$` lines
+pub(crate) struct SDBlockReader<R: BufRead> {
+    reader: R,
+    line: Vec<u8>,
+    line_number: usize,
+    block_line: usize,
+    finished: bool,
+}
+
+impl<R: BufRead> SDBlockReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        SDBlockReader {
+            reader,
+            line: Vec::new(),
+            line_number: 0,
+            block_line: 1,
+            finished: false,
+        }
+    }
+
+    /// The line on which the last returned block starts
+    pub(crate) fn block_line(&self) -> usize {
+        self.block_line
+    }
+
+    /// Returns the next block, the text after the last terminator is returned as a final (possibly empty) block.
+    /// A read error ends the input.
+    pub(crate) fn next_block(&mut self) -> Option<io::Result<String>> {
+        if self.finished {
+            return None;
+        }
+        let mut block = String::new();
+        self.block_line = self.line_number + 1;
+        loop {
+            self.line.clear();
+            let n = match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+            if n == 0 {
+                self.finished = true;
+                return Some(Ok(block));
+            }
+            self.line_number += 1;
+            let line = String::from_utf8_lossy(&self.line);
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if line.trim_end() == "$$$$" {
+                return Some(Ok(block));
+            }
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+}
+
+/// Iterates lazily over the records of an SD file
+pub struct SDIterator<R: BufRead = Box<dyn BufRead + Send>> {
+    blocks: SDBlockReader<R>,
+    index: usize,
+}
+
+impl SDIterator<Box<dyn BufRead + Send>> {
+    /// Opens a plain, gzip or zstd compressed SD file
+    pub fn new(pathname: &str) -> Self {
+        let reader = compression::open_reader(pathname).expect("Could not load file.");
+        SDIterator::from_reader(reader)
+    }
+}
+
+impl<R: BufRead> SDIterator<R> {
+    /// Reads from any buffered source e.g. stdin or a decompressor
+    pub fn from_reader(reader: R) -> Self {
+        SDIterator {
+            blocks: SDBlockReader::new(reader),
+            index: 0,
+        }
+    }
+
+    /// The next unparsed record with its index and starting line, or the read error which ended the input
+    pub(crate) fn next_raw(&mut self) -> Option<(usize, usize, io::Result<String>)> {
+        loop {
+            let block = self.blocks.next_block()?;
+            if matches!(&block, Ok(block) if block.trim().is_empty()) {
+                continue; // skip empty delimiter blocks
+            }
+            let index = self.index;
+            self.index += 1;
+            return Some((index, self.blocks.block_line(), block));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SDIterator<R> {
+    type Item = SDRecord;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, line, block) = self.next_raw()?;
+        Some(SDRecord::from_raw(index, line, block))
+    }
+}
+
+/// Molblocks with more atoms or bonds are always written in the V3000 format
+const MAX_V2000_ENTRIES: usize = 999;
+
+/// Writes molecules with title lines and data fields to any writer
+pub struct SDWriter<W: Write> {
+    out: W,
+    force_v3000: bool,
+}
+
+impl SDWriter<CompressedWriter> {
+    /// Creates an SD file, compressed if the name ends with `.gz` or `.zst`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(SDWriter::new(compression::create_writer(path)?))
+    }
+
+    /// Completes the file, a compressed file is only valid after this succeeded
+    pub fn finish(self) -> io::Result<()> {
+        self.out.finish()
+    }
+}
+
+impl<W: Write> SDWriter<W> {
+    pub fn new(out: W) -> Self {
+        SDWriter {
+            out,
+            force_v3000: false,
+        }
+    }
+
+    /// Write V3000 molblocks for all molecules, by default this is only done for more than 999 atoms or bonds
+    pub fn set_force_v3000(&mut self, force_v3000: bool) {
+        self.force_v3000 = force_v3000;
+    }
+
+    /// Writes a record, fails for records without a valid molecule
+    pub fn write_record(&mut self, record: &SDRecord) -> io::Result<()> {
+        let mol = record.mol.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Record without molecule: {}", record.title),
+            )
+        })?;
+        self.write_molecule(mol, &record.title, &record.props)
+    }
+
+    /// Writes a molblock with the given title line, followed by the data fields and the `$$$$` terminator.
+    /// A blank line ends an SD data item, so blank lines within values are not written and values containing
+    /// them do not round trip. Values read from SD files never contain blank lines.
+    pub fn write_molecule(
+        &mut self,
+        mol: &Molecule,
+        title: &str,
+        props: &IndexMap<String, String>,
+    ) -> io::Result<()> {
+        let json_mol = mol.get_json_molecule();
+        let v3000 = self.force_v3000
+            || json_mol.atoms.len() > MAX_V2000_ENTRIES
+            || json_mol.bonds.len() > MAX_V2000_ENTRIES;
+        let molblock = if v3000 {
+            mol.get_v3kmolblock("")
+        } else {
+            mol.get_molblock("")
+        };
+        // the first line of a molblock is the title
+        let body = molblock
+            .split_once('\n')
+            .map(|(_, body)| body)
+            .unwrap_or("");
+        writeln!(self.out, "{}", title.lines().next().unwrap_or(""))?;
+        self.out.write_all(body.as_bytes())?;
+        if !body.ends_with('\n') {
+            writeln!(self.out)?;
+        }
+        for (key, value) in props.iter() {
+            writeln!(self.out, ">  <{}>", key)?;
+            // a blank line would terminate the data item, readers would skip the rest of the value
+            for line in value.lines().filter(|l| !l.trim().is_empty()) {
+                writeln!(self.out, "{}", line)?;
+            }
+            writeln!(self.out)?;
+        }
+        writeln!(self.out, "$$$$")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
@@ -0,0 +1,280 @@
+//! Random access to the records of large SD files
+//!
+//! The file is scanned once for the byte offsets of its records, afterwards single records or ranges are
+//! parsed on demand. The offsets can be saved as a sidecar index next to the SD file, which is used by
+//! [`IndexedSDReader::open`] as long as the SD file keeps its size, modification time and the content of its
+//! first and last 64 KiB.
+//!
+//! Sidecar layout (all integers little endian):
+//!
+//! | content                               | size          |
+//! |---------------------------------------|---------------|
+//! | magic `RDKSDIX2`                      | 8             |
+//! | size of the SD file                   | u64           |
+//! | modification time in ns, 0 if unknown | u64           |
+//! | FNV-1a hash of first and last 64 KiB  | u64           |
+//! | record count                          | u64           |
+//! | start, end and first line             | count x 3 u64 |
+//!
+//! ```no_run
+//! use rdkitcffi::IndexedSDReader;
+//!
+//! let reader = IndexedSDReader::open("library.sdf").unwrap();
+//! reader.save_index().unwrap();
+//! println!("{} records", reader.len());
+//! for record in reader.range(1000..1010) {
+//!     println!("{} {:?}", record.title, record.mol.map(|m| m.get_smiles("")));
+//! }
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use memmap2::Mmap;
+
+use crate::compression::Compression;
+use crate::sdf::{SDBlockReader, SDRecord};
+
+const MAGIC: &[u8; 8] = b"RDKSDIX2";
+const HEADER_SIZE: usize = 40;
+const ENTRY_SIZE: usize = 24;
+/// Bytes at the start and at the end of the SD file which are hashed to detect edits
+const HASHED_BYTES: usize = 65536;
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Identifies the state of the SD file an index was built for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    size: u64,
+    modified: u64,
+    hash: u64,
+}
+
+impl Stamp {
+    fn new(file: &File, data: &[u8]) -> Stamp {
+        let modified = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_nanos() as u64);
+        let head = &data[..data.len().min(HASHED_BYTES)];
+        let tail = &data[data.len().saturating_sub(HASHED_BYTES)..];
+        Stamp {
+            size: data.len() as u64,
+            modified,
+            hash: fnv1a(tail, fnv1a(head, FNV_OFFSET)),
+        }
+    }
+}
+
+fn fnv1a(data: &[u8], mut hash: u64) -> u64 {
+    for &b in data {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Byte range and first line of a record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    start: u64,
+    end: u64,
+    line: u64,
+}
+
+/// Returns the records of an SD file, empty blocks between `$$$$` lines are skipped like in the SDIterator
+fn scan(data: &[u8]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut start = 0;
+    let mut start_line = 1;
+    let mut pos = 0;
+    let mut line = 0;
+    let mut push = |start: usize, end: usize, first_line: usize| {
+        if !data[start..end].iter().all(u8::is_ascii_whitespace) {
+            entries.push(Entry {
+                start: start as u64,
+                end: end as u64,
+                line: first_line as u64,
+            });
+        }
+    };
+    while pos < data.len() {
+        let line_end = match data[pos..].iter().position(|&b| b == b'\n') {
+            Some(i) => pos + i + 1,
+            None => data.len(),
+        };
+        line += 1;
+        if data[pos..line_end].trim_ascii_end() == b"$$$$" {
+            push(start, pos, start_line);
+            start = line_end;
+            start_line = line + 1;
+        }
+        pos = line_end;
+    }
+    push(start, data.len(), start_line);
+    entries
+}
+
+/// Random access reader for uncompressed SD files
+pub struct IndexedSDReader {
+    path: PathBuf,
+    mmap: Mmap,
+    stamp: Stamp,
+    entries: Vec<Entry>,
+}
+
+impl IndexedSDReader {
+    /// Opens an SD file, using its sidecar index if it is up to date and scanning the file otherwise
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<IndexedSDReader> {
+        let path = path.as_ref().to_path_buf();
+        let (mmap, stamp) = Self::map(&path)?;
+        let entries = match Self::read_index(&Self::index_path(&path), stamp) {
+            Ok(entries) => entries,
+            Err(_) => scan(&mmap),
+        };
+        Ok(IndexedSDReader {
+            path,
+            mmap,
+            stamp,
+            entries,
+        })
+    }
+
+    /// Opens an SD file and scans it, ignoring any sidecar index
+    pub fn build<P: AsRef<Path>>(path: P) -> io::Result<IndexedSDReader> {
+        let path = path.as_ref().to_path_buf();
+        let (mmap, stamp) = Self::map(&path)?;
+        let entries = scan(&mmap);
+        Ok(IndexedSDReader {
+            path,
+            mmap,
+            stamp,
+            entries,
+        })
+    }
+
+    fn map(path: &Path) -> io::Result<(Mmap, Stamp)> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        if Compression::from_magic(&mmap) != Compression::None {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Compressed SD files can not be indexed",
+            ));
+        }
+        let stamp = Stamp::new(&file, &mmap);
+        Ok((mmap, stamp))
+    }
+
+    /// The sidecar index of an SD file, `<file name>.idx`
+    pub fn index_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut name = path.as_ref().as_os_str().to_os_string();
+        name.push(".idx");
+        PathBuf::from(name)
+    }
+
+    fn read_index(index_path: &Path, stamp: Stamp) -> io::Result<Vec<Entry>> {
+        let mut data: Vec<u8> = Vec::new();
+        File::open(index_path)?.read_to_end(&mut data)?;
+        let u64_at = |pos: usize| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&data[pos..pos + 8]);
+            u64::from_le_bytes(buf)
+        };
+        if data.len() < HEADER_SIZE || &data[..8] != MAGIC {
+            return Err(invalid_data("Not an SD index file"));
+        }
+        let indexed = Stamp {
+            size: u64_at(8),
+            modified: u64_at(16),
+            hash: u64_at(24),
+        };
+        if indexed != stamp {
+            return Err(invalid_data("SD index does not match the SD file"));
+        }
+        let count = u64_at(32) as usize;
+        if count.checked_mul(ENTRY_SIZE).map(|n| n + HEADER_SIZE) != Some(data.len()) {
+            return Err(invalid_data("Truncated SD index"));
+        }
+        let entries: Vec<Entry> = (0..count)
+            .map(|i| {
+                let pos = HEADER_SIZE + i * ENTRY_SIZE;
+                Entry {
+                    start: u64_at(pos),
+                    end: u64_at(pos + 8),
+                    line: u64_at(pos + 16),
+                }
+            })
+            .collect();
+        if entries
+            .iter()
+            .any(|e| e.start > e.end || e.end > stamp.size)
+        {
+            return Err(invalid_data("SD index does not match the SD file"));
+        }
+        Ok(entries)
+    }
+
+    /// Writes the record offsets to the sidecar index
+    pub fn save_index(&self) -> io::Result<()> {
+        self.write_index(Self::index_path(&self.path))
+    }
+
+    /// Writes the record offsets to the given file
+    pub fn write_index<P: AsRef<Path>>(&self, index_path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(index_path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&self.stamp.size.to_le_bytes())?;
+        out.write_all(&self.stamp.modified.to_le_bytes())?;
+        out.write_all(&self.stamp.hash.to_le_bytes())?;
+        out.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for e in self.entries.iter() {
+            out.write_all(&e.start.to_le_bytes())?;
+            out.write_all(&e.end.to_le_bytes())?;
+            out.write_all(&e.line.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The raw text of record n
+    pub fn get_block(&self, n: usize) -> Option<String> {
+        let e = self.entries.get(n)?;
+        let bytes = &self.mmap[e.start as usize..e.end as usize];
+        // normalizes line endings the same way as the streaming reader
+        SDBlockReader::new(bytes).next_block()?.ok()
+    }
+
+    /// Parses record n
+    pub fn get(&self, n: usize) -> Option<SDRecord> {
+        let block = self.get_block(n)?;
+        Some(SDRecord::parse(&block, n, self.entries[n].line as usize))
+    }
+
+    /// Parses the records of a range, indices beyond the end are ignored
+    pub fn range(&self, range: Range<usize>) -> impl Iterator<Item = SDRecord> + '_ {
+        let end = range.end.min(self.len());
+        (range.start.min(end)..end).filter_map(move |n| self.get(n))
+    }
+
+    /// Parses all records
+    pub fn iter(&self) -> impl Iterator<Item = SDRecord> + '_ {
+        self.range(0..self.len())
+    }
+}
@@ -0,0 +1,470 @@
+//! Streaming access to SMILES files with configurable columns
+//!
+//! By default columns are separated by whitespace, the first column contains the SMILES, the second the name
+//! and lines starting with `#` are skipped. Any other columns are kept as properties. A line with an invalid
+//! or missing SMILES still gives a record with its name and columns, explained by `error`. Reading stops at
+//! the first line which can not be read, e.g. in a corrupt `.smi.gz`, and that error is returned as one more
+//! record.
+//!
+//! ```no_run
+//! use rdkitcffi::smiles::SmilesReader;
+//!
+//! let reader = SmilesReader::new("compounds.csv")
+//!     .with_delimiter(Some(','))
+//!     .with_title_line(true)
+//!     .with_smiles_column(1)
+//!     .with_name_column(Some(0));
+//! for record in reader {
+//!     println!("{} {:?} {:?}", record.name, record.mol, record.props);
+//! }
+//! ```
+//!
+//! Standardized molecules can be written back in the same shape with a [`SmilesWriter`]:
+//!
+//! ```no_run
+//! use rdkitcffi::smiles::{SmilesReader, SmilesWriter};
+//!
+//! let mut writer = SmilesWriter::create("standardized.smi")
+//!     .unwrap()
+//!     .with_delimiter('\t')
+//!     .with_title_line(true)
+//!     .with_failed_input(true);
+//! for mut record in SmilesReader::new("data/ringtest.smi") {
+//!     if let Some(mol) = record.mol.as_mut() {
+//!         mol.cleanup("");
+//!     }
+//!     writer.write_record(&record).unwrap();
+//! }
+//! writer.finish().unwrap();
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use indexmap::IndexMap;
+
+use crate::compression::{self, CompressedWriter};
+use crate::error::{self, RecordError};
+use crate::Molecule;
+
+/// A single line of a SMILES file
+#[derive(Debug)]
+pub struct SmilesRecord {
+    /// The molecule, None if it could not be parsed
+    pub mol: Option<Molecule>,
+    /// The SMILES as read from the file
+    pub smiles: String,
+    /// The content of the name column, empty if there is none
+    pub name: String,
+    /// All other columns, named by the title line or as `column_<index>`
+    pub props: IndexMap<String, String>,
+    /// Position of the record in the file, the title line is not counted
+    pub index: usize,
+    /// Line number in the file
+    pub line: usize,
+    /// Why the molecule could not be parsed, or why reading the input failed
+    pub error: Option<RecordError>,
+}
+
+impl SmilesRecord {
+    /// The molecule, or the error of a missing or invalid SMILES or of an unreadable line
+    pub fn into_result(self) -> Result<Molecule, RecordError> {
+        error::record_result(self.mol, self.error, self.index, self.line, self.name)
+    }
+}
+
+/// Column layout of a SMILES file, splits lines into records
+#[derive(Clone, Debug)]
+pub(crate) struct SmilesParser {
+    delimiter: Option<char>,
+    smiles_column: usize,
+    name_column: Option<usize>,
+    column_names: Option<Vec<String>>,
+}
+
+impl SmilesParser {
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self.delimiter {
+            Some(d) => line.split(d).map(|c| c.trim()).collect(),
+            None => line.split_whitespace().collect(),
+        }
+    }
+
+    fn column_name(&self, index: usize) -> String {
+        match self
+            .column_names
+            .as_ref()
+            .and_then(|names| names.get(index))
+        {
+            Some(name) => name.clone(),
+            None => format!("column_{}", index),
+        }
+    }
+
+    /// Parses a line, index and line number are used for error reporting
+    pub(crate) fn parse(&self, line: &str, index: usize, line_number: usize) -> SmilesRecord {
+        let columns = self.split(line);
+        let smiles = columns
+            .get(self.smiles_column)
+            .cloned()
+            .unwrap_or("")
+            .to_string();
+        let name = self
+            .name_column
+            .and_then(|c| columns.get(c).cloned())
+            .unwrap_or("")
+            .to_string();
+        let mut props: IndexMap<String, String> = IndexMap::new();
+        for (i, value) in columns.iter().enumerate() {
+            if i != self.smiles_column && Some(i) != self.name_column {
+                props.insert(self.column_name(i), value.to_string());
+            }
+        }
+        let (mol, error) = if smiles.is_empty() {
+            let error = RecordError {
+                index,
+                line: line_number,
+                title: name.clone(),
+                message: format!("Missing SMILES in column {}", self.smiles_column),
+            };
+            (None, Some(error))
+        } else {
+            error::parse_record(&smiles, index, line_number, &name)
+        };
+        SmilesRecord {
+            mol,
+            smiles,
+            name,
+            props,
+            index,
+            line: line_number,
+            error,
+        }
+    }
+
+    /// Parses a line, or reports the I/O error which stopped the reader in a record of its own
+    pub(crate) fn parse_raw(
+        &self,
+        line: io::Result<String>,
+        index: usize,
+        line_number: usize,
+    ) -> SmilesRecord {
+        match line {
+            Ok(line) => self.parse(&line, index, line_number),
+            Err(e) => SmilesRecord {
+                mol: None,
+                smiles: String::new(),
+                name: String::new(),
+                props: IndexMap::new(),
+                index,
+                line: line_number,
+                error: Some(error::read_error(index, line_number, &e)),
+            },
+        }
+    }
+}
+
+/// Iterates lazily over the records of a SMILES file, blank lines are skipped
+pub struct SmilesReader<R: BufRead = Box<dyn BufRead + Send>> {
+    reader: R,
+    parser: SmilesParser,
+    title_line: bool,
+    comment_prefix: Option<String>,
+    line: Vec<u8>,
+    line_number: usize,
+    index: usize,
+    /// The read error which ended the input, reported with the next record
+    read_error: Option<io::Error>,
+    finished: bool,
+}
+
+impl SmilesReader<Box<dyn BufRead + Send>> {
+    /// Opens a plain, gzip or zstd compressed SMILES file
+    pub fn new(pathname: &str) -> Self {
+        let reader = compression::open_reader(pathname).expect("Could not load file.");
+        SmilesReader::from_reader(reader)
+    }
+}
+
+impl<R: BufRead> SmilesReader<R> {
+    /// Reads from any buffered source e.g. stdin or a decompressor
+    pub fn from_reader(reader: R) -> Self {
+        SmilesReader {
+            reader,
+            parser: SmilesParser {
+                delimiter: None,
+                smiles_column: 0,
+                name_column: Some(1),
+                column_names: None,
+            },
+            title_line: false,
+            comment_prefix: Some("#".to_string()),
+            line: Vec::new(),
+            line_number: 0,
+            index: 0,
+            read_error: None,
+            finished: false,
+        }
+    }
+
+    /// The column separator, None splits on any whitespace
+    pub fn with_delimiter(mut self, delimiter: Option<char>) -> Self {
+        self.parser.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_smiles_column(mut self, column: usize) -> Self {
+        self.parser.smiles_column = column;
+        self
+    }
+
+    pub fn with_name_column(mut self, column: Option<usize>) -> Self {
+        self.parser.name_column = column;
+        self
+    }
+
+    /// Whether the first (non comment) line contains the column names
+    pub fn with_title_line(mut self, title_line: bool) -> Self {
+        self.title_line = title_line;
+        self
+    }
+
+    /// Lines starting with this prefix are skipped
+    pub fn with_comment_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.comment_prefix = prefix.map(|p| p.to_string());
+        self
+    }
+
+    /// The column names of the title line, available after the first record was read
+    pub fn column_names(&self) -> Option<&Vec<String>> {
+        self.parser.column_names.as_ref()
+    }
+
+    /// Returns the next line which is neither blank nor a comment, a read error is kept and ends the input
+    fn next_line(&mut self) -> Option<String> {
+        while !self.finished {
+            self.line.clear();
+            let n = match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.read_error = Some(e);
+                    0
+                }
+            };
+            if n == 0 {
+                self.finished = true;
+                return None;
+            }
+            self.line_number += 1;
+            let line = String::from_utf8_lossy(&self.line);
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(prefix) = &self.comment_prefix {
+                if line.starts_with(prefix.as_str()) {
+                    continue;
+                }
+            }
+            return Some(line.to_string());
+        }
+        None
+    }
+
+    /// Reads the column names if there is a title line which was not read yet
+    pub(crate) fn read_title(&mut self) {
+        if self.title_line && self.parser.column_names.is_none() {
+            if let Some(title) = self.next_line() {
+                let names = self.parser.split(&title);
+                self.parser.column_names = Some(names.iter().map(|c| c.to_string()).collect());
+            }
+        }
+    }
+
+    /// The column layout, complete once the title line was read
+    pub(crate) fn parser(&self) -> &SmilesParser {
+        &self.parser
+    }
+
+    /// The next unparsed record line with its index and line number, or the read error which ended the input
+    pub(crate) fn next_raw(&mut self) -> Option<(usize, usize, io::Result<String>)> {
+        self.read_title();
+        let line = match self.next_line() {
+            Some(line) => Ok(line),
+            None => Err(self.read_error.take()?),
+        };
+        let index = self.index;
+        self.index += 1;
+        Some((index, self.line_number, line))
+    }
+}
+
+impl<R: BufRead> Iterator for SmilesReader<R> {
+    type Item = SmilesRecord;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, line_number, line) = self.next_raw()?;
+        Some(self.parser.parse_raw(line, index, line_number))
+    }
+}
+
+/// Written for empty fields if the columns are separated by whitespace, where an empty field would vanish
+const EMPTY_FIELD: &str = "-";
+
+/// Writes molecules as SMILES with name and property columns.
+///
+/// Every field has to be read back as one column: a field containing the delimiter or a line break, or any
+/// whitespace if the delimiter is whitespace, is an `InvalidInput` error and nothing is written for the
+/// record. Empty fields are written as `-` if the delimiter is whitespace.
+pub struct SmilesWriter<W: Write> {
+    out: W,
+    delimiter: char,
+    title_line: bool,
+    cxsmiles: bool,
+    failed_input: bool,
+    json_info: String,
+    columns: Option<Vec<String>>,
+    started: bool,
+}
+
+impl SmilesWriter<CompressedWriter> {
+    /// Creates a SMILES file, compressed if the name ends with `.gz` or `.zst`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(SmilesWriter::new(compression::create_writer(path)?))
+    }
+
+    /// Completes the file, a compressed file is only valid after this succeeded
+    pub fn finish(self) -> io::Result<()> {
+        self.out.finish()
+    }
+}
+
+impl<W: Write> SmilesWriter<W> {
+    /// Creates a writer with space separated columns and without title line
+    pub fn new(out: W) -> Self {
+        SmilesWriter {
+            out,
+            delimiter: ' ',
+            title_line: false,
+            cxsmiles: false,
+            failed_input: false,
+            json_info: String::new(),
+            columns: None,
+            started: false,
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether a line with the column names is written first
+    pub fn with_title_line(mut self, title_line: bool) -> Self {
+        self.title_line = title_line;
+        self
+    }
+
+    /// Write CXSMILES instead of SMILES, their extensions are separated by a space so another delimiter
+    /// is needed
+    pub fn with_cxsmiles(mut self, cxsmiles: bool) -> Self {
+        self.cxsmiles = cxsmiles;
+        self
+    }
+
+    /// Write the original input of records which could not be parsed instead of skipping them
+    pub fn with_failed_input(mut self, failed_input: bool) -> Self {
+        self.failed_input = failed_input;
+        self
+    }
+
+    /// Json options passed to the SMILES generation
+    pub fn with_smiles_options(mut self, json_info: &str) -> Self {
+        self.json_info = json_info.to_string();
+        self
+    }
+
+    /// The property columns to write, by default the properties of the first record are used
+    pub fn with_columns(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    /// Writes a record, records without molecule are skipped unless failed input is written
+    pub fn write_record(&mut self, record: &SmilesRecord) -> io::Result<()> {
+        match &record.mol {
+            Some(mol) => self.write_molecule(mol, &record.name, &record.props),
+            None if self.failed_input => {
+                self.write_line(&record.smiles, &record.name, &record.props)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_molecule(
+        &mut self,
+        mol: &Molecule,
+        name: &str,
+        props: &IndexMap<String, String>,
+    ) -> io::Result<()> {
+        let smiles = if self.cxsmiles {
+            mol.get_cxsmiles(&self.json_info)
+        } else {
+            mol.get_smiles(&self.json_info)
+        };
+        self.write_line(&smiles, name, props)
+    }
+
+    fn write_line(
+        &mut self,
+        smiles: &str,
+        name: &str,
+        props: &IndexMap<String, String>,
+    ) -> io::Result<()> {
+        if !self.started {
+            if self.columns.is_none() {
+                self.columns = Some(props.keys().cloned().collect());
+            }
+            if self.title_line {
+                let mut title = vec!["SMILES", "Name"];
+                for column in self.columns.iter().flatten() {
+                    title.push(self.field(column)?);
+                }
+                writeln!(self.out, "{}", title.join(&self.delimiter.to_string()))?;
+            }
+            self.started = true;
+        }
+        let mut fields = vec![self.field(smiles)?, self.field(name)?];
+        for column in self.columns.iter().flatten() {
+            fields.push(self.field(props.get(column).map(|v| v.as_str()).unwrap_or(""))?);
+        }
+        writeln!(self.out, "{}", fields.join(&self.delimiter.to_string()))
+    }
+
+    /// The field as written, an error if it would not be read back as one column
+    fn field<'a>(&self, value: &'a str) -> io::Result<&'a str> {
+        let whitespace = self.delimiter.is_whitespace();
+        if value.contains(['\n', '\r'])
+            || value.contains(self.delimiter)
+            || (whitespace && value.contains(char::is_whitespace))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Field {:?} would be split into several columns", value),
+            ));
+        }
+        if value.is_empty() && whitespace {
+            Ok(EMPTY_FIELD)
+        } else {
+            Ok(value)
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
@@ -0,0 +1,152 @@
+//! A substructure search library, screening candidates with pattern fingerprints before the actual
+//! substructure match is done.
+//!
+//! ```no_run
+//! use rdkitcffi::substruct::SubstructLibrary;
+//! use rdkitcffi::Molecule;
+//!
+//! let mut library = SubstructLibrary::new();
+//! for mol in rdkitcffi::read_sdfile_unwrap("data/test.sdf").iter() {
+//!     library.add_molecule(mol);
+//! }
+//! let query = Molecule::get_qmol("c1ccccc1", "").unwrap();
+//! let hits: Vec<usize> = library.search(&query, None, true);
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+use rayon::prelude::*;
+use serde_json::Value;
+
+use crate::bindings::{free_ptr, get_substruct_match};
+use crate::fingerprint::{self, FingerprintKind};
+use crate::Molecule;
+
+// number of screened candidates matched per parallel batch when the number of hits is limited
+const BATCH_SIZE: usize = 1024;
+
+/// Holds molecule pickles together with their pattern fingerprints
+pub struct SubstructLibrary {
+    fp_options: String,
+    pickles: Vec<Vec<u8>>,
+    fps: Vec<Vec<u8>>,
+}
+
+impl Default for SubstructLibrary {
+    fn default() -> Self {
+        SubstructLibrary::new()
+    }
+}
+
+/// Checks a single molecule pickle against the query pickle
+fn has_match(pkl: &[u8], query_pkl: &[u8], json_info: &CString) -> bool {
+    unsafe {
+        let res_cchar = get_substruct_match(
+            pkl.as_ptr() as *const c_char,
+            pkl.len(),
+            query_pkl.as_ptr() as *const c_char,
+            query_pkl.len(),
+            json_info.as_ptr(),
+        );
+        if res_cchar.is_null() {
+            return false;
+        }
+        let res = CStr::from_ptr(res_cchar).to_string_lossy().into_owned();
+        free_ptr(res_cchar);
+        match serde_json::from_str::<Value>(&res) {
+            Ok(v) => v
+                .get("atoms")
+                .and_then(Value::as_array)
+                .is_some_and(|atoms| !atoms.is_empty()),
+            Err(_) => false,
+        }
+    }
+}
+
+impl SubstructLibrary {
+    /// Creates an empty library using pattern fingerprints with default settings
+    pub fn new() -> SubstructLibrary {
+        SubstructLibrary::with_fp_options("")
+    }
+
+    /// Creates an empty library, the json options are passed to the pattern fingerprint e.g. `{"nBits":1024}`
+    pub fn with_fp_options(json_info: &str) -> SubstructLibrary {
+        SubstructLibrary {
+            fp_options: json_info.to_string(),
+            pickles: Vec::new(),
+            fps: Vec::new(),
+        }
+    }
+
+    /// Adds a molecule and returns its index
+    pub fn add_molecule(&mut self, mol: &Molecule) -> usize {
+        self.pickles.push(mol.pickle().to_vec());
+        self.fps
+            .push(FingerprintKind::Pattern.compute(mol, &self.fp_options));
+        self.pickles.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.pickles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pickles.is_empty()
+    }
+
+    /// Indices of all molecules whose pattern fingerprint contains all bits of the query fingerprint
+    pub fn screen(&self, query: &Molecule) -> Vec<usize> {
+        let query_fp = FingerprintKind::Pattern.compute(query, &self.fp_options);
+        self.fps
+            .iter()
+            .enumerate()
+            .filter(|(_, fp)| fingerprint::is_subset(&query_fp, fp))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the sorted indices of molecules containing the query, stopping after max_hits matches.
+    /// Setting parallel does the substructure matching in a thread pool.
+    pub fn search(&self, query: &Molecule, max_hits: Option<usize>, parallel: bool) -> Vec<usize> {
+        self.search_with_args(query, "", max_hits, parallel)
+    }
+
+    /// Like search, the json options are passed to the substructure match
+    pub fn search_with_args(
+        &self,
+        query: &Molecule,
+        json_info: &str,
+        max_hits: Option<usize>,
+        parallel: bool,
+    ) -> Vec<usize> {
+        let json_info = CString::new(json_info).unwrap();
+        let query_pkl = query.pickle();
+        let candidates = self.screen(query);
+        let max_hits = max_hits.unwrap_or(usize::MAX);
+        let matches = |i: &usize| has_match(&self.pickles[*i], query_pkl, &json_info);
+
+        if !parallel {
+            return candidates
+                .into_iter()
+                .filter(matches)
+                .take(max_hits)
+                .collect();
+        }
+        let mut hits: Vec<usize> = Vec::new();
+        let batch_size = if max_hits == usize::MAX {
+            candidates.len().max(1)
+        } else {
+            BATCH_SIZE
+        };
+        for batch in candidates.chunks(batch_size) {
+            let batch_hits: Vec<usize> = batch.par_iter().cloned().filter(matches).collect();
+            hits.extend(batch_hits);
+            if hits.len() >= max_hits {
+                hits.truncate(max_hits);
+                break;
+            }
+        }
+        hits
+    }
+}
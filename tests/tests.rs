@@ -1,11 +1,27 @@
 extern crate rdkitcffi;
+use indexmap::IndexMap;
+use rdkitcffi::align::AtomSelection;
 use rdkitcffi::bindings::{disable_logging, enable_logging, free_ptr, version};
+use rdkitcffi::cluster::butina_cluster;
+use rdkitcffi::compression::{create_writer, decompress};
+use rdkitcffi::fingerprint::{popcount, FingerprintKind};
+use rdkitcffi::fpdb::{FpDb, FpDbWriter};
+use rdkitcffi::fps::{to_hex, FpsHeader, FpsReader, FpsRecord, FpsWriter};
+use rdkitcffi::geometry;
+use rdkitcffi::isotope_pattern::{Adduct, IsotopePattern, IsotopeTable};
+use rdkitcffi::periodic_table;
+use rdkitcffi::picker::{maxmin_pick, MaxMinPicker, Seed};
+use rdkitcffi::smiles::{SmilesReader, SmilesRecord, SmilesWriter};
+use rdkitcffi::substruct::SubstructLibrary;
 use rdkitcffi::{
-    json::jsonfrom_string, prefer_coordgen, read_sdfile, read_sdfile_unwrap, read_smifile_unwrap,
-    JsonMolecule, Molecule, SDIterator,
+    json::jsonfrom_string, prefer_coordgen, rdkit_version, read_sdfile, read_sdfile_unwrap,
+    read_smifile, read_smifile_unwrap, AtomStereo, BondOrder, BondStereo, CipLabel, CoordsError,
+    Element, IndexedSDReader, JsonAtom, JsonBase, JsonBond, JsonMolecule, Molecule, RecordError,
+    SDIterator, SDRecord, SDWriter,
 };
 use serde_json::json;
 use std::ffi::CStr;
+use std::io::Write;
 
 #[test]
 fn basics() {
@@ -49,6 +65,369 @@ fn smifile2molecules() {
     assert_eq!(mol_list.len(), 11);
 }
 
+#[test]
+fn smiles_reader() {
+    let records: Vec<SmilesRecord> = SmilesReader::new("data/ringtest.smi").collect();
+    assert_eq!(records.len(), 11);
+    assert!(records.iter().all(|r| r.mol.is_some()));
+    assert_eq!(records[1].name, "barrelene");
+    assert_eq!(records[1].smiles, "C1=CC2C=CC1C=C2");
+
+    let csv = "# exported from registry\nid\tsmiles\tpIC50\nA-1\tCCO\t5.1\n\nA-2\tc1ccccc1O\t6.3\nA-3\tC1CC\t7.0\n";
+    let mut reader = SmilesReader::from_reader(csv.as_bytes())
+        .with_delimiter(Some('\t'))
+        .with_title_line(true)
+        .with_smiles_column(1)
+        .with_name_column(Some(0));
+    let first = reader.next().unwrap();
+    assert_eq!(
+        reader.column_names().unwrap(),
+        &vec!["id".to_string(), "smiles".to_string(), "pIC50".to_string()]
+    );
+    assert_eq!(first.name, "A-1");
+    assert_eq!(first.props["pIC50"], "5.1");
+    assert_eq!(first.mol.unwrap().get_smiles(""), "CCO");
+    let rest: Vec<SmilesRecord> = reader.collect();
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[0].name, "A-2");
+    assert!(rest[1].mol.is_none());
+    assert_eq!(rest[1].smiles, "C1CC");
+}
+
+#[test]
+fn smiles_writer() {
+    let csv = "id,smiles,pIC50\nA-1,OCC,5.1\nA-2,C1CC,6.3\nA-3,[O-]C(=O)CC,7.0\n";
+    let reader = SmilesReader::from_reader(csv.as_bytes())
+        .with_delimiter(Some(','))
+        .with_title_line(true)
+        .with_smiles_column(1)
+        .with_name_column(Some(0));
+    let mut writer = SmilesWriter::new(Vec::new())
+        .with_delimiter('\t')
+        .with_title_line(true)
+        .with_failed_input(true);
+    for record in reader {
+        writer.write_record(&record).unwrap();
+    }
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        output,
+        "SMILES\tName\tpIC50\nCCO\tA-1\t5.1\nC1CC\tA-2\t6.3\nCCC(=O)[O-]\tA-3\t7.0\n"
+    );
+
+    let mut writer = SmilesWriter::new(Vec::new()).with_columns(&["missing"]);
+    let mut props = IndexMap::new();
+    props.insert("other".to_string(), "1".to_string());
+    let mol = Molecule::new("CO |$C2;O1$|").unwrap();
+    writer.write_molecule(&mol, "methanol", &props).unwrap();
+    let mut writer = SmilesWriter::new(writer.into_inner()).with_cxsmiles(true);
+    let e = writer.write_molecule(&mol, "methanol", &props).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    let mut writer = SmilesWriter::new(writer.into_inner())
+        .with_cxsmiles(true)
+        .with_delimiter('\t');
+    writer.write_molecule(&mol, "methanol", &props).unwrap();
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(output, "CO methanol -\nCO |$C2;O1$|\tmethanol\t1\n");
+}
+
+#[test]
+fn smiles_writer_roundtrip() {
+    let smi = "CCO ethanol 1 a\nc1ccccc1 benzene 2 b\nC1CC cyclopropane 3\nCN methylamine\n";
+    let mut records: Vec<SmilesRecord> = SmilesReader::from_reader(smi.as_bytes()).collect();
+    records[1].name.clear();
+    let mut writer = SmilesWriter::new(Vec::new())
+        .with_failed_input(true)
+        .with_columns(&["column_2", "column_3"]);
+    for record in records.iter() {
+        writer.write_record(record).unwrap();
+    }
+    let mut record = SmilesReader::from_reader("CC ethyl 3 d".as_bytes())
+        .next()
+        .unwrap();
+    record.name = "two words".to_string();
+    let e = writer.write_record(&record).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    record.name = "line\nbreak".to_string();
+    assert!(writer.write_record(&record).is_err());
+
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    let read_back: Vec<SmilesRecord> = SmilesReader::from_reader(output.as_bytes()).collect();
+    assert_eq!(read_back.len(), records.len());
+    for (new, old) in read_back.iter().zip(records.iter()) {
+        let name = if old.name.is_empty() { "-" } else { &old.name };
+        assert_eq!(new.name, name);
+        assert_eq!(new.props.len(), 2);
+        for (column, value) in new.props.iter() {
+            let old_value = old.props.get(column).map_or("", |v| v.as_str());
+            assert_eq!(value, if old_value.is_empty() { "-" } else { old_value });
+        }
+    }
+
+    let csv = "SMILES,Name,note\nCCO,,\"a b\"\nC1CC,cyclopropane,\n";
+    let reader = SmilesReader::from_reader(csv.as_bytes())
+        .with_delimiter(Some(','))
+        .with_title_line(true);
+    let mut writer = SmilesWriter::new(Vec::new())
+        .with_delimiter(',')
+        .with_title_line(true)
+        .with_failed_input(true);
+    let records: Vec<SmilesRecord> = reader.collect();
+    for record in records.iter() {
+        writer.write_record(record).unwrap();
+    }
+    let output = String::from_utf8(writer.into_inner()).unwrap();
+    let read_back: Vec<SmilesRecord> = SmilesReader::from_reader(output.as_bytes())
+        .with_delimiter(Some(','))
+        .with_title_line(true)
+        .collect();
+    assert_eq!(read_back.len(), records.len());
+    for (new, old) in read_back.iter().zip(records.iter()) {
+        assert_eq!(new.name, old.name);
+        assert_eq!(new.props, old.props);
+    }
+}
+
+#[test]
+fn compressed_files() {
+    let tmp = std::env::temp_dir();
+    let sdf = std::fs::read_to_string("data/test.sdf").unwrap();
+    let smi = std::fs::read_to_string("data/ringtest.smi").unwrap();
+    let sdf_gz = tmp.join("rdkitcffi_compressed.sdf.gz");
+    let smi_zst = tmp.join("rdkitcffi_compressed.smi.zst");
+    let mut writer = create_writer(&sdf_gz).unwrap();
+    writer.write_all(sdf.as_bytes()).unwrap();
+    writer.finish().unwrap();
+    let mut writer = create_writer(&smi_zst).unwrap();
+    writer.write_all(smi.as_bytes()).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(std::fs::read(&sdf_gz).unwrap()[..2], [0x1f, 0x8b]);
+
+    assert_eq!(
+        SDIterator::new(sdf_gz.to_str().unwrap()).count(),
+        SDIterator::new("data/test.sdf").count()
+    );
+    assert_eq!(read_sdfile_unwrap(sdf_gz.to_str().unwrap()).len(), 8);
+    assert_eq!(read_smifile_unwrap(smi_zst.to_str().unwrap()).len(), 11);
+    assert_eq!(SmilesReader::new(smi_zst.to_str().unwrap()).count(), 11);
+
+    // compression is detected by content, not by name
+    let renamed = tmp.join("rdkitcffi_compressed_renamed.sdf");
+    std::fs::copy(&sdf_gz, &renamed).unwrap();
+    assert_eq!(SDIterator::new(renamed.to_str().unwrap()).count(), 8);
+
+    let out = tmp.join("rdkitcffi_compressed_out.sdf.zst");
+    let mut writer = SDWriter::create(&out).unwrap();
+    for record in SDIterator::new(sdf_gz.to_str().unwrap()) {
+        writer.write_record(&record).unwrap();
+    }
+    writer.finish().unwrap();
+    assert_eq!(SDIterator::new(out.to_str().unwrap()).count(), 8);
+    for path in [sdf_gz, smi_zst, renamed, out].iter() {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn finished_compressed_writer() {
+    let tmp = std::env::temp_dir();
+    let record = SmilesRecord {
+        mol: None,
+        smiles: "not a smiles".to_string(),
+        name: "broken".to_string(),
+        props: IndexMap::new(),
+        index: 0,
+        line: 1,
+        error: None,
+    };
+    for name in ["finished.smi", "finished.smi.gz", "finished.smi.zst"].iter() {
+        let path = tmp.join(format!("rdkitcffi_{}", name));
+        let mut writer = SmilesWriter::create(&path).unwrap().with_failed_input(true);
+        for _ in 0..1000 {
+            writer.write_record(&record).unwrap();
+        }
+        writer.finish().unwrap();
+        let content = rdkitcffi::compression::read_to_string(&path).unwrap();
+        assert_eq!(content, "not a smiles broken\n".repeat(1000));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn corrupt_compressed_input() {
+    let corrupt: &'static [u8] = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03not deflate data at all";
+    let records: Vec<SDRecord> = SDIterator::from_reader(decompress(corrupt).unwrap()).collect();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].mol.is_none());
+    let message = &records[0].error.as_ref().unwrap().message;
+    assert!(message.starts_with("Could not read input"), "{}", message);
+    let records: Vec<SDRecord> = SDIterator::from_reader(decompress(corrupt).unwrap())
+        .parallel(2, 10)
+        .collect();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].error.is_some());
+
+    let records: Vec<SmilesRecord> =
+        SmilesReader::from_reader(decompress(corrupt).unwrap()).collect();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].error.is_some());
+    let records: Vec<SmilesRecord> = SmilesReader::from_reader(decompress(corrupt).unwrap())
+        .parallel(2, 10)
+        .collect();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].error.is_some());
+}
+
+#[test]
+fn record_errors() {
+    let tmp = std::env::temp_dir();
+    let sdf = std::fs::read_to_string("data/test.sdf").unwrap();
+    let first = &sdf[..sdf.find("$$$$").unwrap()];
+    let broken = "broken\n  no counts line\n\nM  END\n";
+    let sd_path = tmp.join("rdkitcffi_record_errors.sdf");
+    std::fs::write(&sd_path, format!("{}$$$$\n$$$$\n{}$$$$\n", first, broken)).unwrap();
+    let results = read_sdfile(sd_path.to_str().unwrap());
+    // the empty block between the delimiters is not a record
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    let error = results[1].as_ref().unwrap_err();
+    assert_eq!(error.index, 1);
+    assert_eq!(error.line, first.lines().count() + 3);
+    assert_eq!(error.title, "broken");
+    assert!(!error.message.is_empty());
+    let records: Vec<SDRecord> = SDIterator::new(sd_path.to_str().unwrap()).collect();
+    assert_eq!(records[1].index, 1);
+    assert!(records[0].error.is_none());
+    assert_eq!(records[1].error.as_ref(), Some(error));
+
+    let smi_path = tmp.join("rdkitcffi_record_errors.smi");
+    std::fs::write(&smi_path, "CCO ethanol\n\nC1CC broken\nc1ccccc1 benzene\n").unwrap();
+    let results: Vec<Result<Molecule, RecordError>> = read_smifile(smi_path.to_str().unwrap());
+    assert_eq!(results.len(), 3);
+    let error = results[1].as_ref().unwrap_err();
+    assert_eq!((error.index, error.line), (1, 3));
+    assert_eq!(error.title, "broken");
+    assert!(error.to_string().starts_with("record 1 (line 3, broken): "));
+    let records: Vec<SmilesRecord> = SmilesReader::new(smi_path.to_str().unwrap()).collect();
+    let error = records[1].error.as_ref().unwrap();
+    assert_eq!(
+        (error.index, error.line, error.title.as_str()),
+        (1, 3, "broken")
+    );
+    assert!(records[2].error.is_none());
+    std::fs::remove_file(sd_path).unwrap();
+    std::fs::remove_file(smi_path).unwrap();
+}
+
+#[test]
+fn indexed_sd_reader() {
+    let path = std::env::temp_dir().join("rdkitcffi_indexed.sdf");
+    std::fs::copy("data/test.sdf", &path).unwrap();
+    let reader = IndexedSDReader::build(&path).unwrap();
+    assert_eq!(reader.len(), 8);
+    let streamed: Vec<SDRecord> = SDIterator::new("data/test.sdf").collect();
+    let last = reader.get(7).unwrap();
+    assert_eq!(last.title, streamed[7].title);
+    assert_eq!(last.line, streamed[7].line);
+    assert_eq!(
+        last.mol.unwrap().get_smiles(""),
+        streamed[7].mol.as_ref().unwrap().get_smiles("")
+    );
+    assert!(reader.get(8).is_none());
+    let titles: Vec<String> = reader.range(2..4).map(|r| r.title).collect();
+    assert_eq!(
+        titles,
+        vec![streamed[2].title.clone(), streamed[3].title.clone()]
+    );
+
+    reader.save_index().unwrap();
+    let index_path = IndexedSDReader::index_path(&path);
+    assert!(index_path.exists());
+    let reopened = IndexedSDReader::open(&path).unwrap();
+    assert_eq!(reopened.len(), 8);
+    assert_eq!(reopened.get_block(5), reader.get_block(5));
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(index_path).unwrap();
+}
+
+#[test]
+fn stale_sd_index() {
+    let path = std::env::temp_dir().join("rdkitcffi_stale_index.sdf");
+    std::fs::write(&path, "ab\n$$$$\nabcd\n$$$$\n").unwrap();
+    let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+    IndexedSDReader::build(&path).unwrap().save_index().unwrap();
+    // same size and modification time, different record boundaries
+    std::fs::write(&path, "abcd\n$$$$\nab\n$$$$\n").unwrap();
+    let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_modified(modified).unwrap();
+    drop(file);
+    let reader = IndexedSDReader::open(&path).unwrap();
+    assert_eq!(reader.get_block(0).unwrap(), "abcd\n");
+    assert_eq!(reader.get_block(1).unwrap(), "ab\n");
+    std::fs::remove_file(IndexedSDReader::index_path(&path)).unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn parallel_parsing() {
+    let sequential: Vec<SDRecord> = SDIterator::new("data/test.sdf").collect();
+    let parallel: Vec<SDRecord> = SDIterator::new("data/test.sdf").parallel(4, 3).collect();
+    assert_eq!(parallel.len(), sequential.len());
+    for (a, b) in sequential.iter().zip(parallel.iter()) {
+        assert_eq!(
+            (a.index, a.line, &a.title, &a.props),
+            (b.index, b.line, &b.title, &b.props)
+        );
+        assert_eq!(a.error, b.error);
+        // coordinates, title and stereo are kept, not only the connectivity
+        assert_eq!(
+            a.mol.as_ref().map(|m| (m.get_molblock(""), m.get_json(""))),
+            b.mol.as_ref().map(|m| (m.get_molblock(""), m.get_json("")))
+        );
+    }
+
+    let sequential: Vec<SmilesRecord> = SmilesReader::new("data/ringtest.smi").collect();
+    let parallel: Vec<SmilesRecord> = SmilesReader::new("data/ringtest.smi")
+        .parallel(0, 2)
+        .collect();
+    assert_eq!(parallel.len(), 11);
+    for (a, b) in sequential.iter().zip(parallel.iter()) {
+        assert_eq!(
+            (a.index, a.line, &a.name, &a.props),
+            (b.index, b.line, &b.name, &b.props)
+        );
+        assert_eq!(a.error, b.error);
+        assert_eq!(
+            a.mol.as_ref().map(|m| m.get_json("")),
+            b.mol.as_ref().map(|m| m.get_json(""))
+        );
+    }
+}
+
+#[test]
+fn parallel_error_messages() {
+    // each broken SMILES is quoted in its own error message
+    let broken: Vec<String> = (1..=64).map(|n| format!("C1{}", "C".repeat(n))).collect();
+    let mut smi = String::new();
+    for (i, smiles) in broken.iter().enumerate() {
+        smi.push_str(&format!("{} broken{}\nc1ccccc1 ok{}\n", smiles, i, i));
+    }
+    let records: Vec<SmilesRecord> =
+        SmilesReader::from_reader(std::io::Cursor::new(smi.into_bytes()))
+            .parallel(4, 1)
+            .collect();
+    assert_eq!(records.len(), 2 * broken.len());
+    for (i, smiles) in broken.iter().enumerate() {
+        let record = &records[2 * i];
+        assert!(records[2 * i + 1].mol.is_some());
+        let message = &record.error.as_ref().unwrap().message;
+        assert!(message.contains(&format!("'{}'", smiles)), "{}", message);
+        for other in broken.iter().filter(|o| *o != smiles) {
+            assert!(!message.contains(&format!("'{}'", other)), "{}", message);
+        }
+    }
+}
+
 /*     #[test]
 fn sditerator() {
     let sdreader = SDIterator::new("data/test.sdf");
@@ -60,9 +439,12 @@ fn sditerator() {
 
 #[test]
 fn sdfile2molecules() {
-    let mut mol_list: Vec<Option<Molecule>> = read_sdfile("data/test.sdf");
+    let mut mol_list: Vec<Result<Molecule, RecordError>> = read_sdfile("data/test.sdf");
     println!("mols: {}", mol_list.len());
-    let mut valid_mols: Vec<_> = mol_list.iter_mut().filter_map(Option::as_mut).collect();
+    let mut valid_mols: Vec<_> = mol_list
+        .iter_mut()
+        .filter_map(|m| m.as_mut().ok())
+        .collect();
 
     for (i, mol) in valid_mols.iter_mut().enumerate() {
         mol.remove_all_hs();
@@ -75,7 +457,8 @@ fn sdfile2molecules() {
             mol.get_numbonds(),
         );
     }
-    assert_eq!(mol_list.len(), 9);
+    // the empty block after the last $$$$ is not counted
+    assert_eq!(mol_list.len(), 8);
 }
 #[test]
 fn sdfile2molecules_win() {
@@ -412,7 +795,7 @@ fn set_3d_coords_for_problem_molecule() -> Result<(), String> {
 
     // Verify we have coordinates
     assert!(mol.has_coords());
-    let coords = mol.get_coords();
+    let coords = mol.get_coords().unwrap();
     assert!(!coords.is_empty(), "expected 3D coordinates");
     assert_eq!(coords[0].len(), 3, "expected x,y,z per atom");
 
@@ -437,11 +820,85 @@ fn reionize_test() {
 #[test]
 fn sd_iterator() {
     let sdreader = SDIterator::new("data/test.sdf");
-    let mols: Vec<_> = sdreader.flatten().collect(); // flatten Option<Molecule>
+    let mols: Vec<_> = sdreader.filter_map(|r| r.mol).collect();
     assert!(!mols.is_empty());
     assert!(mols.iter().all(|m| !m.get_smiles("").is_empty()));
 }
 
+#[test]
+fn sd_iterator_streaming() {
+    let sdf = std::fs::read_to_string("data/test.sdf").unwrap();
+    let sdf = sdf.replacen("(1) \n", "(1) \nprice $$$$ high\n", 1);
+    assert!(sdf.contains("price $$$$ high"));
+    let sdreader = SDIterator::from_reader(std::io::Cursor::new(sdf));
+    let records: Vec<SDRecord> = sdreader.collect();
+    assert_eq!(records.len(), 8);
+    assert!(records.iter().all(|r| r.mol.is_some()));
+    assert_eq!(records[0].props["ACTIVITY"], "price $$$$ high\n7.92");
+
+    let sdreader = SDIterator::new("data/test_win.sdf");
+    assert_eq!(sdreader.filter_map(|r| r.mol).count(), 19);
+}
+
+#[test]
+fn sd_data_fields() {
+    let records: Vec<SDRecord> = SDIterator::new("data/test.sdf").collect();
+    assert_eq!(records[0].title, "COO_23A");
+    let keys: Vec<&str> = records[0].props.keys().map(|k| k.as_str()).collect();
+    assert_eq!(keys, vec!["ACTIVITY", "SET", "Min_dist", "Avg_dist"]);
+    assert_eq!(records[0].props["ACTIVITY"], "7.92");
+    assert_eq!(records[0].props["Avg_dist"], "999.9");
+    assert_eq!(records[1].title, "COO_23E");
+
+    let records: Vec<SDRecord> = SDIterator::new("data/test_win.sdf").collect();
+    assert!(records.iter().all(|r| !r.props.is_empty()));
+    assert!(records
+        .iter()
+        .all(|r| r.props.values().all(|v| !v.contains('\r'))));
+}
+
+#[test]
+fn sd_writer_roundtrip() {
+    let records: Vec<SDRecord> = SDIterator::new("data/test.sdf").collect();
+    let mut writer = SDWriter::new(Vec::new());
+    for record in records.iter() {
+        writer.write_record(record).unwrap();
+    }
+    let mut record = SDIterator::new("data/test.sdf").next().unwrap();
+    record.title = "v3000".to_string();
+    record
+        .props
+        .insert("NOTE".to_string(), "first\n\n  \nlast".to_string());
+    record
+        .props
+        .insert("LINES".to_string(), "one\ntwo".to_string());
+    writer.set_force_v3000(true);
+    writer.write_record(&record).unwrap();
+    let sdf = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(sdf.matches("$$$$\n").count(), records.len() + 1);
+
+    let written: Vec<SDRecord> = SDIterator::from_reader(sdf.as_bytes()).collect();
+    assert_eq!(written.len(), records.len() + 1);
+    for (orig, new) in records.iter().zip(written.iter()) {
+        assert_eq!(orig.title, new.title);
+        assert_eq!(orig.props, new.props);
+        assert_eq!(
+            orig.mol.as_ref().unwrap().get_smiles(""),
+            new.mol.as_ref().unwrap().get_smiles("")
+        );
+    }
+    let last = written.last().unwrap();
+    assert_eq!(last.title, "v3000");
+    // blank lines can not be part of an SD data item and are dropped
+    assert_eq!(last.props["NOTE"], "first\nlast");
+    assert_eq!(last.props["LINES"], "one\ntwo");
+    assert_eq!(
+        last.mol.as_ref().unwrap().get_smiles(""),
+        records[0].mol.as_ref().unwrap().get_smiles("")
+    );
+    assert!(sdf.contains("V3000"));
+}
+
 #[test]
 fn get_commonchem_test() {
     let mol = Molecule::new("CCO").unwrap();
@@ -452,6 +909,474 @@ fn get_commonchem_test() {
     assert_eq!(cc.molecules[0].bonds.len(), 2);
 }
 
+#[test]
+fn molecule_from_json_molecule() {
+    let mol = Molecule::new("C[C@H](N)c1ccccc1O").unwrap();
+    let json_mol = mol.get_json_molecule();
+    let rebuilt = Molecule::from_json_molecule(&json_mol).unwrap();
+    assert_eq!(rebuilt.get_smiles(""), mol.get_smiles(""));
+    let from_base = Molecule::new(&mol.get_commonchem().to_json()).unwrap();
+    assert_eq!(from_base.get_smiles(""), mol.get_smiles(""));
+
+    // ethanol built from scratch, then oxidized to acetaldehyde
+    let mut atoms = vec![JsonAtom::new(6), JsonAtom::new(6), JsonAtom::new(8)];
+    atoms[0].impHs = 3;
+    atoms[1].impHs = 2;
+    atoms[2].impHs = 1;
+    let mut json_mol = JsonMolecule {
+        name: "ethanol".to_string(),
+        atoms,
+        bonds: vec![JsonBond::new(0, 1, 1), JsonBond::new(1, 2, 1)],
+        conformers: Vec::new(),
+        properties: Default::default(),
+        extensions: Vec::new(),
+    };
+    let ethanol = Molecule::from_json_molecule(&json_mol).unwrap();
+    assert_eq!(ethanol.get_smiles(""), "CCO");
+    json_mol.atoms[1].impHs = 1;
+    json_mol.atoms[2].impHs = 0;
+    json_mol.bonds[1].bo = 2;
+    let acetaldehyde = Molecule::from_json_molecule(&json_mol).unwrap();
+    assert_eq!(acetaldehyde.get_smiles(""), "CC=O");
+}
+
+#[test]
+fn cis_trans_json_roundtrip() {
+    let mol = Molecule::new("F/C=C/F").unwrap();
+    let json_mol = mol.get_json_molecule();
+    assert_eq!(json_mol.bonds[1].stereo, "trans");
+    assert_eq!(json_mol.bonds[1].stereoAtoms, vec![0, 3]);
+    let rebuilt = Molecule::from_json_molecule(&json_mol).unwrap();
+    assert_eq!(rebuilt.get_smiles(""), "F/C=C/F");
+    let cis = Molecule::new("F/C=C\\F").unwrap();
+    let rebuilt = Molecule::from_json_molecule(&cis.get_json_molecule()).unwrap();
+    assert_eq!(rebuilt.get_smiles(""), cis.get_smiles(""));
+}
+
+#[test]
+fn typed_atoms_and_bonds() {
+    let mol = Molecule::new("C[C@H]([O-])c1ccccc1/C=C/[13CH3]").unwrap();
+    let atoms = mol.atoms();
+    assert_eq!(atoms.len(), mol.get_numatoms());
+    assert_eq!(atoms[0].element, Some(Element::C));
+    assert_eq!(atoms[0].implicit_hs, 3);
+    assert!(matches!(atoms[1].stereo, AtomStereo::Cw | AtomStereo::Ccw));
+    assert_eq!(atoms[2].element, Some(Element::O));
+    assert_eq!(atoms[2].formal_charge, -1);
+    assert!(atoms[3].is_aromatic && atoms[3].is_in_ring);
+    assert!(!atoms[1].is_aromatic && !atoms[1].is_in_ring);
+    assert_eq!(atoms[11].isotope, Some(13));
+    assert_eq!(atoms[0].isotope, None);
+
+    let bonds = mol.bonds();
+    assert_eq!(bonds.len(), mol.get_numbonds());
+    assert_eq!(bonds[0].order, BondOrder::Single);
+    assert!(bonds.iter().filter(|b| b.is_aromatic).count() == 6);
+    assert!(bonds.iter().filter(|b| b.is_in_ring).all(|b| b.is_aromatic));
+    let double = bonds
+        .iter()
+        .find(|b| !b.is_aromatic && b.order == BondOrder::Double);
+    assert_ne!(double.unwrap().stereo, BondStereo::Unspecified);
+}
+
+#[test]
+fn rdkit_extension() {
+    let mol = Molecule::new("C/C=C/[C@H](O)c1ccc2ccccc2c1").unwrap();
+    let ext = mol.get_rdkit_extension().unwrap();
+    assert_eq!(ext.aromatic_atoms.len(), 10);
+    assert_eq!(ext.aromatic_bonds.len(), 11);
+    assert_eq!(ext.atom_rings.len(), 2);
+    assert_eq!(
+        ext.aromatic_systems(),
+        vec![(5..15).collect::<Vec<usize>>()]
+    );
+    assert!(matches!(
+        ext.atom_cip.get(&3),
+        Some(CipLabel::R) | Some(CipLabel::S)
+    ));
+    assert_eq!(ext.atom_cip.len(), 1);
+    assert_eq!(ext.bond_cip.len(), 1);
+    assert_eq!(ext.bond_cip.get(&1), Some(&CipLabel::E));
+
+    let cis = Molecule::new("C/C=C\\CO").unwrap();
+    let ext = cis.get_rdkit_extension().unwrap();
+    assert_eq!(ext.bond_cip.len(), 1);
+    assert_eq!(ext.bond_cip.get(&1), Some(&CipLabel::Z));
+}
+
+#[test]
+fn molecule_graph() {
+    let mol = Molecule::new("OCc1ccc2ccccc2c1").unwrap();
+    let graph = mol.graph();
+    assert_eq!(graph.num_atoms(), mol.get_numatoms());
+    assert_eq!(graph.neighbours(1), vec![0, 2]);
+    assert_eq!(graph.neighbours(2), vec![1, 3, 11]);
+    assert_eq!(graph.degree(2), 3);
+    assert_eq!(graph.heavy_degree(2), 3);
+    assert_eq!(graph.total_hs(0), 1);
+    assert_eq!(graph.total_hs(1), 2);
+    assert_eq!(graph.bond_between(1, 2), Some(1));
+    assert_eq!(graph.bond_between(0, 3), None);
+
+    assert_eq!(graph.rings().len(), 2);
+    assert!(!graph.is_in_ring(1));
+    assert!(graph.is_in_ring(2));
+    assert_eq!(graph.ring_count(5), 2);
+    assert_eq!(graph.smallest_ring_size(2), Some(6));
+
+    assert_eq!(graph.shortest_path(0, 3), Some(vec![0, 1, 2, 3]));
+    let distances = graph.distance_matrix();
+    assert_eq!(distances[0][3], Some(3));
+    assert_eq!(distances[3][0], Some(3));
+    assert_eq!(distances[3][3], Some(0));
+
+    let salt = Molecule::new("CC(=O)[O-].[Na+]").unwrap().graph();
+    assert_eq!(salt.shortest_path(0, 4), None);
+    assert_eq!(
+        salt.distances_from(0),
+        vec![Some(0), Some(1), Some(2), Some(2), None]
+    );
+}
+
+#[test]
+fn periodic_table_data() {
+    let bromine = periodic_table::from_symbol("Br").unwrap();
+    assert_eq!(bromine.z, 35);
+    assert_eq!(bromine.name, "Bromine");
+    assert_eq!(Element::Br.symbol(), "Br");
+    assert_eq!(Element::from_symbol("Br"), Some(Element::Br));
+    assert_eq!(Element::Br.data(), bromine);
+    for z in 1..=118 {
+        let element = Element::from_z(z).unwrap();
+        assert_eq!(element.z() as i32, z);
+        assert_eq!(format!("{:?}", element), element.symbol());
+        assert_eq!(Element::from_symbol(element.symbol()), Some(element));
+    }
+    assert_eq!(Element::from_symbol("*"), Some(Element::Dummy));
+    assert_eq!(Element::from_z(119), None);
+    assert_eq!(Element::from_symbol("Xx"), None);
+    let isotopes = periodic_table::isotopes(35);
+    assert_eq!(isotopes.len(), 2);
+    let abundance: f64 = isotopes.iter().map(|i| i.abundance).sum();
+    assert!((abundance - 1.0).abs() < 1e-6);
+    let average: f64 = isotopes.iter().map(|i| i.abundance * i.mass).sum();
+    assert!((average - bromine.average_mass).abs() < 0.01);
+    assert_eq!(
+        periodic_table::most_abundant_isotope(35)
+            .unwrap()
+            .mass_number,
+        79
+    );
+    assert_eq!(periodic_table::monoisotopic_mass(6), Some(12.0));
+    assert_eq!(periodic_table::monoisotopic_mass(43), None);
+    assert_eq!(periodic_table::element(16).unwrap().valences, &[2, 4, 6]);
+    assert_eq!(
+        periodic_table::element(6).unwrap().covalent_radius,
+        Some(0.76)
+    );
+    assert!(periodic_table::element(119).is_none());
+
+    let json_mol = Molecule::new("[Na+].[O-]c1ccccc1Cl")
+        .unwrap()
+        .get_json_molecule();
+    let symbols: Vec<&str> = json_mol.atoms.iter().filter_map(|a| a.symbol()).collect();
+    assert_eq!(symbols, vec!["Na", "O", "C", "C", "C", "C", "C", "C", "Cl"]);
+}
+
+#[test]
+fn molecular_formula() {
+    let mol = Molecule::new("CC(=O)Oc1ccccc1C(=O)O").unwrap();
+    assert_eq!(mol.formula().to_string(), "C9H8O4");
+    let desc = mol.get_descriptors_as_dict();
+    assert!((mol.exact_mass() - desc["exactmw"] as f64).abs() < 1e-3);
+    assert!((mol.formula().average_mass() - desc["amw"] as f64).abs() < 1e-2);
+
+    assert_eq!(
+        Molecule::new("CC(=O)[O-]").unwrap().formula().to_string(),
+        "C2H3O2-"
+    );
+    assert_eq!(
+        Molecule::new("[NH3+]CCCC[NH3+]")
+            .unwrap()
+            .formula()
+            .to_string(),
+        "C4H14N2+2"
+    );
+    assert_eq!(
+        Molecule::new("[Na+].[Cl-]").unwrap().formula().to_string(),
+        "ClNa"
+    );
+
+    let labelled = Molecule::new("[13CH3]C([2H])([2H])O").unwrap();
+    let formula = labelled.formula();
+    assert_eq!(formula.to_string(), "C[13C]H4[2H]2O");
+    assert_eq!(formula.count(1), 6);
+    assert_eq!(formula.isotope_count(6, 13), 1);
+    let desc = labelled.get_descriptors_as_dict();
+    assert!((labelled.exact_mass() - desc["exactmw"] as f64).abs() < 1e-3);
+}
+
+#[test]
+fn isotope_patterns() {
+    let caffeine = Molecule::new("Cn1cnc2c1c(=O)n(C)c(=O)n2C")
+        .unwrap()
+        .formula();
+    assert_eq!(caffeine.to_string(), "C8H10N4O2");
+    let mz = |name: &str| Adduct::parse(name).unwrap().mz(&caffeine).unwrap();
+    assert!((mz("[M+H]+") - 195.087652).abs() < 1e-4);
+    assert!((mz("[M+Na]+") - 217.069596).abs() < 1e-4);
+    assert!((mz("[M-H]-") - 193.073100).abs() < 1e-4);
+    assert!((Adduct::protonated(2).mz(&caffeine).unwrap() - 98.047464).abs() < 1e-4);
+    assert!(Adduct::parse("[M+H]⁺").is_none());
+    assert!(Adduct::parse("[M+H]²+").is_none());
+    assert!(Adduct::parse("[M+Ñ]+").is_none());
+    assert!(Adduct::parse("[M−H]-").is_none());
+
+    let pattern = IsotopePattern::new(&caffeine);
+    let total: f64 = pattern.peaks().iter().map(|p| p.probability).sum();
+    assert!((total - 1.0).abs() < 1e-4);
+    let centroided = pattern.centroid(1000.0).unwrap();
+    let intensities = centroided.relative_intensities();
+    assert!((centroided.peaks()[0].mass - caffeine.monoisotopic_mass()).abs() < 1e-6);
+    assert!(intensities[1] > 9.5 && intensities[1] < 11.0);
+    assert!(pattern.centroid(0.0).is_none());
+    assert!(pattern.centroid(-1000.0).is_none());
+    assert!(pattern.centroid(f64::NAN).is_none());
+    assert!(IsotopeTable::new(&caffeine, &Adduct::common(), Some(f64::INFINITY)).is_none());
+
+    let table = IsotopeTable::new(&caffeine, &Adduct::common(), Some(20000.0)).unwrap();
+    let df = table.to_dataframe().unwrap();
+    assert_eq!(df.height(), table.len());
+    assert_eq!(
+        df.get_column_names(),
+        vec![
+            "adduct",
+            "charge",
+            "offset",
+            "mz",
+            "probability",
+            "intensity"
+        ]
+    );
+}
+
+#[test]
+fn multiple_molecules_and_conformers() {
+    let json = r#"{"commonchem":{"version":10},"defaults":{"atom":{"z":6,"impHs":0,"chg":0,"nRad":0,"isotope":0,"stereo":"unspecified"},"bond":{"bo":1,"stereo":"unspecified"}},
+        "molecules":[
+            {"name":"methanol","atoms":[{"impHs":3},{"z":8,"impHs":1}],"bonds":[{"atoms":[0,1]}]},
+            {"name":"ethane","atoms":[{"impHs":3},{"impHs":3}],"bonds":[{"atoms":[0,1]}],
+             "conformers":[{"dim":3,"coords":[[0.0,0.0,0.0],[1.54,0.0,0.0]]},{"dim":3,"coords":[[0.0,0.0,0.0],[0.0,1.54,0.0]]}]}
+        ]}"#;
+    let json_mols = JsonMolecule::json_mols_from_json(json).unwrap();
+    assert_eq!(json_mols.len(), 2);
+    assert_eq!(json_mols[0].num_conformers(), 0);
+    assert!(json_mols[0].conformer(0).is_none());
+    assert_eq!(json_mols[1].num_conformers(), 2);
+    assert_eq!(
+        json_mols[1].conformer(1).unwrap().coords[1],
+        vec![0.0, 1.54, 0.0]
+    );
+    assert_eq!(
+        JsonMolecule::json_mol_from_json(json).unwrap().name,
+        "methanol"
+    );
+    assert!(JsonMolecule::json_mol_from_json("{}").is_err());
+    let empty = JsonBase::from_molecules(Vec::new()).to_json();
+    assert!(JsonMolecule::json_mol_from_json(&empty).is_err());
+
+    let ethane = Molecule::from_json_molecule(&json_mols[1]).unwrap();
+    assert_eq!(ethane.get_smiles(""), "CC");
+    assert_eq!(ethane.num_conformers(), 2);
+    assert_eq!(ethane.get_coords().unwrap()[1], vec![1.54, 0.0, 0.0]);
+    let second = ethane.conformer(1).unwrap();
+    assert_eq!(second.dim, 3);
+    assert_eq!(second.coords[1], vec![0.0, 1.54, 0.0]);
+    assert!(ethane.conformer(2).is_none());
+
+    let mut mol = Molecule::new("CCO").unwrap();
+    assert_eq!(mol.num_conformers(), 0);
+    assert!(mol.get_coords().is_none());
+    assert!(mol.conformer(0).is_none());
+    mol.set_2d_coords();
+    assert_eq!(mol.conformer(0).unwrap().dim, 2);
+    assert_eq!(mol.get_coords().unwrap().len(), 3);
+}
+
+#[test]
+fn set_coordinates() {
+    let mut mol = Molecule::new("C[C@H](O)/C=C/C").unwrap();
+    let smiles = mol.get_smiles("");
+    mol.set_3d_coords(r#"{"randomSeed": 42, "useExpTorsionAnglePrefs": false}"#);
+    let coords = mol.get_coords().unwrap();
+    let shifted: Vec<[f32; 3]> = coords.iter().map(|c| [c[0] + 1.0, c[1], c[2]]).collect();
+    mol.set_coords(&shifted).unwrap();
+    assert_eq!(mol.get_smiles(""), smiles);
+    assert_eq!(mol.num_conformers(), 1);
+    for (new, old) in mol.get_coords().unwrap().iter().zip(coords.iter()) {
+        assert!((new[0] - old[0] - 1.0).abs() < 1e-3);
+        assert!((new[2] - old[2]).abs() < 1e-3);
+    }
+
+    assert_eq!(
+        mol.set_coords(&shifted[1..]),
+        Err(CoordsError::AtomCount {
+            atoms: shifted.len(),
+            coords: shifted.len() - 1
+        })
+    );
+    assert_eq!(
+        mol.set_conformer_coords(2, &shifted),
+        Err(CoordsError::NoConformer {
+            index: 2,
+            conformers: 1
+        })
+    );
+    mol.set_conformer_coords(1, &shifted).unwrap();
+    assert_eq!(mol.num_conformers(), 2);
+    assert!((mol.conformer(1).unwrap().coords[0][0] - shifted[0][0]).abs() < 1e-3);
+
+    let mut flat = Molecule::new("CCO").unwrap();
+    flat.set_coords(&[[0.0, 0.0, 0.0], [1.5, 0.0, 0.0], [2.2, 1.2, 0.0]])
+        .unwrap();
+    assert!(flat.has_coords());
+    assert_eq!(flat.conformer(0).unwrap().dim, 3);
+    flat.set_2d_coords();
+    flat.set_coords(&[[0.0, 0.0, 0.0], [1.3, 0.75, 0.0], [2.6, 0.0, 0.0]])
+        .unwrap();
+    let conformer = flat.conformer(0).unwrap();
+    assert_eq!(conformer.dim, 2);
+    assert!((conformer.coords[1][1] - 0.75).abs() < 1e-3);
+
+    let mut json_mol = Molecule::new("CCO").unwrap().get_json_molecule();
+    json_mol.name = "ethanol".to_string();
+    json_mol
+        .properties
+        .insert("source".to_string(), json!("vendor"));
+    let mut named = Molecule::from_json_molecule(&json_mol).unwrap();
+    named
+        .set_coords(&[[0.0, 0.0, 0.0], [1.5, 0.0, 0.0], [2.2, 1.2, 0.0]])
+        .unwrap();
+    let json_mol = named.get_json_molecule();
+    assert_eq!(json_mol.name, "ethanol");
+    assert_eq!(json_mol.properties["source"], json!("vendor"));
+    assert_eq!(json_mol.num_conformers(), 1);
+}
+
+#[test]
+fn geometry_of_conformers() {
+    let mut mol = Molecule::new("CCCC").unwrap();
+    mol.set_3d_coords(r#"{"randomSeed": 42, "useExpTorsionAnglePrefs": false}"#);
+    let json_mol = mol.get_json_molecule();
+    let points = geometry::points(json_mol.conformer(0).unwrap());
+    assert_eq!(points.len(), json_mol.atoms.len());
+    let bond = geometry::distance(points[0], points[1]);
+    assert!((bond - 1.53).abs() < 0.05, "{}", bond);
+    let angle = geometry::angle(points[0], points[1], points[2]);
+    assert!(angle > 105.0 && angle < 118.0, "{}", angle);
+    let torsion = geometry::dihedral(points[0], points[1], points[2], points[3]);
+    assert!(torsion > -180.0 && torsion <= 180.0);
+    assert!(
+        (geometry::dihedral(points[3], points[2], points[1], points[0]) - torsion).abs() < 1e-6
+    );
+    assert!(geometry::bonded_angles(&json_mol.bonds).contains(&[0, 1, 2]));
+    assert!(geometry::torsions(&json_mol.bonds).contains(&[0, 1, 2, 3]));
+
+    let distances = geometry::distance_matrix(&points);
+    assert!((distances[0][1] - bond).abs() < 1e-12);
+    assert_eq!(distances[1][0], distances[0][1]);
+    let (min, max) = geometry::bounding_box(&points).unwrap();
+    let centroid = geometry::centroid(&points).unwrap();
+    assert!((0..3).all(|k| min[k] <= centroid[k] && centroid[k] <= max[k]));
+    let axes = geometry::principal_axes(&points).unwrap();
+    assert!(axes.moments[0] >= axes.moments[1] && axes.moments[1] >= axes.moments[2]);
+}
+
+#[test]
+fn geometry_of_points() {
+    // looking from b to c, a points up and d is turned by the torsion, clockwise is positive
+    let (a, b, c) = ([0.0, 1.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+    let turned = |degrees: f64| {
+        let t = degrees.to_radians();
+        [1.0, t.cos(), t.sin()]
+    };
+    assert!((geometry::angle(a, b, c) - 90.0).abs() < 1e-9);
+    assert!((geometry::dihedral(a, b, c, turned(60.0)) - 60.0).abs() < 1e-9);
+    assert!((geometry::dihedral(a, b, c, turned(-60.0)) + 60.0).abs() < 1e-9);
+    assert!((geometry::dihedral(a, b, c, turned(0.0))).abs() < 1e-9);
+    assert_eq!(geometry::dihedral(a, b, c, [1.0, -1.0, 0.0]), 180.0);
+    assert!((geometry::dihedral(a, b, c, turned(179.0)) - 179.0).abs() < 1e-9);
+    assert!((geometry::dihedral(a, b, c, turned(181.0)) + 179.0).abs() < 1e-9);
+
+    // rectangle of 4 x 2 in a plane through z, rotated by 30 degrees
+    let (sin, cos) = 30f64.to_radians().sin_cos();
+    let rectangle: Vec<[f64; 3]> = [[2.0, 1.0], [2.0, -1.0], [-2.0, 1.0], [-2.0, -1.0]]
+        .iter()
+        .map(|[x, y]| [x * cos - y * sin, x * sin + y * cos, 0.0])
+        .collect();
+    let axes = geometry::principal_axes(&rectangle).unwrap();
+    for (moment, expected) in axes.moments.iter().zip([4.0, 1.0, 0.0]) {
+        assert!((moment - expected).abs() < 1e-9, "{:?}", axes);
+    }
+    let dot = |u: [f64; 3], v: [f64; 3]| (0..3).map(|k| u[k] * v[k]).sum::<f64>();
+    for i in 0..3 {
+        for j in 0..3 {
+            let delta = if i == j { 1.0 } else { 0.0 };
+            assert!((dot(axes.axes[i], axes.axes[j]) - delta).abs() < 1e-9);
+        }
+    }
+    assert!((dot(axes.axes[0], [cos, sin, 0.0]).abs() - 1.0).abs() < 1e-9);
+    assert!((dot(axes.axes[1], [-sin, cos, 0.0]).abs() - 1.0).abs() < 1e-9);
+    assert!((axes.axes[2][2].abs() - 1.0).abs() < 1e-9);
+
+    let mut broken = rectangle.clone();
+    broken[1][2] = f64::NAN;
+    assert!(geometry::principal_axes(&broken).is_none());
+    let pairs = [(0, 0), (1, 1), (2, 2)];
+    assert!(rdkitcffi::align::superpose(&broken, &rectangle, &pairs).is_some());
+    assert!(geometry::dihedral(a, b, c, [f64::NAN, 0.0, 0.0]).is_nan());
+}
+
+#[test]
+fn align_conformers() {
+    let mut mol = Molecule::new("OCCc1ccccc1").unwrap();
+    mol.set_3d_coords(r#"{"randomSeed": 42}"#);
+    let coords = mol.get_coords().unwrap();
+    // rotated by 90 degrees around z and shifted
+    let moved: Vec<[f32; 3]> = coords
+        .iter()
+        .map(|c| [2.0 - c[1], c[0], c[2] - 1.0])
+        .collect();
+    mol.set_conformer_coords(1, &moved).unwrap();
+    assert!(mol.conformer_rmsd(1, 0, &AtomSelection::All).unwrap() > 1.0);
+    let alignment = mol.align_conformers(1, 0, &AtomSelection::All).unwrap();
+    assert!(alignment.rmsd < 1e-3, "{}", alignment.rmsd);
+    assert_eq!(alignment.coords.len(), coords.len());
+    for (new, old) in alignment.coords.iter().zip(coords.iter()) {
+        assert!((0..3).all(|k| (new[k] - old[k] as f64).abs() < 1e-3));
+    }
+    assert!((alignment.rotation[0][1] - 1.0).abs() < 1e-3);
+    let heavy = mol.align_conformers(1, 0, &AtomSelection::HeavyAtoms);
+    assert!(heavy.unwrap().rmsd < 1e-3);
+
+    let query = Molecule::get_qmol("c1ccccc1", "").unwrap();
+    let ring = AtomSelection::from_substruct_match(&mol.get_substruct_match(&query, "")).unwrap();
+    assert_eq!(ring.pairs(&mol.get_json_molecule()).len(), 6);
+    assert!(mol.align_conformers(1, 0, &ring).unwrap().rmsd < 1e-3);
+    assert!(mol.conformer_rmsd(0, 2, &AtomSelection::All).is_none());
+    let missing = AtomSelection::Map(vec![(0, 99)]);
+    assert!(mol.align_conformers(0, 1, &missing).is_none());
+
+    let mut pose = Molecule::new("OCCc1ccccc1").unwrap();
+    pose.set_3d_coords(r#"{"randomSeed": 7}"#);
+    let alignment = pose.align_to(&mol, &ring).unwrap();
+    assert!(alignment.rmsd < 0.1, "{}", alignment.rmsd);
+    pose.set_coords(&alignment.coords_f32()).unwrap();
+    let realigned = pose.align_to(&mol, &ring).unwrap();
+    assert!((realigned.rmsd - alignment.rmsd).abs() < 1e-3);
+    assert!((0..3).all(|k| (realigned.rotation[k][k] - 1.0).abs() < 1e-3));
+}
+
 #[test]
 fn v3kmolblock_content() {
     let mol = Molecule::new("CCO").unwrap();
@@ -461,6 +1386,252 @@ fn v3kmolblock_content() {
     assert!(v3k.contains("BEGIN BOND"));
 }
 
+// ---- fingerprint database tests ----
+
+#[test]
+fn fpdb_roundtrip() {
+    let mol_list: Vec<Molecule> = read_smifile_unwrap("data/ringtest.smi");
+    let options = r#"{"radius":2,"nBits":1024}"#;
+    let mut writer = FpDbWriter::new(FingerprintKind::Morgan, options);
+    writer.add_molecules(&mol_list).unwrap();
+    let path = std::env::temp_dir().join("rdkitcffi_fpdb_roundtrip.fpdb");
+    writer.write(&path).unwrap();
+
+    let db = FpDb::open(&path).unwrap();
+    assert_eq!(db.len(), mol_list.len());
+    assert_eq!(db.num_bits(), 1024);
+    assert_eq!(db.kind(), FingerprintKind::Morgan);
+    assert_eq!(db.options(), options);
+    assert_eq!(db.toolkit_version(), rdkit_version());
+    // records are sorted by popcount
+    let counts: Vec<u32> = (0..db.len())
+        .map(|i| popcount(db.fingerprint(i).unwrap()))
+        .collect();
+    assert!(counts.windows(2).all(|w| w[0] <= w[1]));
+    assert!(db.fingerprint(db.len()).is_none());
+    assert!(db.id(db.len()).is_none());
+    // a molecule finds itself
+    let hits = db.search_molecule(&mol_list[3], 1.0).unwrap();
+    assert!(hits
+        .iter()
+        .any(|(i, sim)| db.id(*i) == Some("3") && *sim == 1.0));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn corrupt_fpdb() {
+    let mut writer = FpDbWriter::new(FingerprintKind::RDKit, "{}");
+    writer.add_fingerprint("first", vec![0xff, 0x01]).unwrap();
+    writer.add_fingerprint("second", vec![0x0f, 0x00]).unwrap();
+    let e = writer.add_fingerprint("third", vec![0x01]).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(writer.len(), 2);
+    let mut data: Vec<u8> = Vec::new();
+    writer.write_to(&mut data).unwrap();
+    let path = std::env::temp_dir().join("rdkitcffi_corrupt.fpdb");
+    let open_with = |bytes: &[u8]| {
+        std::fs::write(&path, bytes).unwrap();
+        FpDb::open(&path)
+    };
+    let db = open_with(&data).unwrap();
+    assert_eq!(db.len(), 2);
+    assert_eq!(db.id(1), Some("first"));
+    assert_eq!(db.fingerprint(1), Some(&[0xff, 0x01][..]));
+    assert_eq!(db.id(2), None);
+    assert_eq!(db.fingerprint(2), None);
+    assert_eq!(
+        db.similarity_search(&[0xff, 0x01], 0.5).unwrap()[0],
+        (1, 1.0)
+    );
+    assert!(db.similarity_search(&[0xff], 0.5).is_err());
+    drop(db);
+    for len in 0..data.len() {
+        let e = open_with(&data[..len]).err().unwrap();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData, "{}", len);
+    }
+
+    let ids_len = "firstsecond".len();
+    let id_offsets = data.len() - ids_len - 3 * 8;
+    let index_end = id_offsets - 2 * 2;
+    let corrupt = |pos: usize, value: u64| {
+        let mut bytes = data.clone();
+        bytes[pos..pos + 8].copy_from_slice(&value.to_le_bytes());
+        open_with(&bytes).err().unwrap().kind()
+    };
+    // record count, popcount index, id offsets
+    assert_eq!(corrupt(24, u64::MAX), std::io::ErrorKind::InvalidData);
+    assert_eq!(corrupt(24, 1 << 60), std::io::ErrorKind::InvalidData);
+    assert_eq!(corrupt(index_end - 8, 3), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        corrupt(id_offsets + 8, 100),
+        std::io::ErrorKind::InvalidData
+    );
+    assert_eq!(corrupt(id_offsets + 16, 4), std::io::ErrorKind::InvalidData);
+    let mut bytes = data.clone();
+    bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(
+        open_with(&bytes).err().unwrap().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+// ---- FPS file tests ----
+
+#[test]
+fn fps_roundtrip() {
+    let mol_list: Vec<Molecule> = read_smifile_unwrap("data/ringtest.smi");
+    let options = r#"{"radius":2,"nBits":512}"#;
+    let header = FpsHeader::for_fingerprint(FingerprintKind::Morgan, options);
+    assert_eq!(
+        header.fp_type.as_deref(),
+        Some("RDKit-Morgan/1 radius=2 fpSize=512")
+    );
+    let path = std::env::temp_dir().join("rdkitcffi_fps_roundtrip.fps");
+    let mut writer = FpsWriter::create(&path, &header).unwrap();
+    for (i, mol) in mol_list.iter().enumerate() {
+        writer.write_molecule(&format!("mol{}", i), mol).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let reader = FpsReader::open(&path).unwrap();
+    assert_eq!(reader.header(), &header);
+    let (kind, read_options) = reader.header().fingerprint_kind().unwrap();
+    assert_eq!(kind, FingerprintKind::Morgan);
+    let records: Vec<FpsRecord> = reader.map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), mol_list.len());
+    for (record, mol) in records.iter().zip(mol_list.iter()) {
+        let expected: Vec<u8> = mol
+            .get_morgan_fp_as_bytes(&read_options)
+            .into_iter()
+            .map(|b| b as u8)
+            .collect();
+        assert_eq!(record.fingerprint, expected);
+    }
+    assert_eq!(records[1].id, "mol1");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fps_read_external() {
+    let fps = "#FPS1\n#num_bits=16\n#type=RDKit-Pattern/4 fpSize=16\n#source=vendor.sdf\n#aromaticity=openeye\n0f81\tabc\tignored\n\nFF00\tdef\n";
+    let reader = FpsReader::new(fps.as_bytes()).unwrap();
+    let header = reader.header().clone();
+    assert_eq!(header.num_bits, Some(16));
+    assert_eq!(header.sources, vec!["vendor.sdf".to_string()]);
+    assert_eq!(
+        header.extra,
+        vec![("aromaticity".to_string(), "openeye".to_string())]
+    );
+    let (kind, options) = header.fingerprint_kind().unwrap();
+    assert_eq!(kind, FingerprintKind::Pattern);
+    assert_eq!(options, r#"{"nBits":16}"#);
+    let records: Vec<FpsRecord> = reader.map(|r| r.unwrap()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].fingerprint, vec![0x0f, 0x81]);
+    assert_eq!(records[1].id, "def");
+    assert_eq!(to_hex(&records[1].fingerprint), "ff00");
+
+    let bad = FpsReader::new("#num_bits=16\n0f\tshort\n".as_bytes()).unwrap();
+    assert!(bad.into_iter().all(|r| r.is_err()));
+
+    let blank = format!("#FPS1\n{}0f81\tlast\n", "\n".repeat(1_000_000));
+    let records: Vec<FpsRecord> = FpsReader::new(blank.as_bytes())
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(records.len(), 1);
+}
+
+// ---- clustering tests ----
+
+#[test]
+fn butina_clustering() {
+    let mol_list: Vec<Molecule> = read_sdfile_unwrap("data/test.sdf");
+    let fps = FingerprintKind::Morgan.compute_all(&mol_list, r#"{"radius":2,"nBits":1024}"#);
+    let clustering = butina_cluster(&fps, 0.7, false);
+    assert_eq!(clustering.assignments.len(), mol_list.len());
+    let mut members: Vec<usize> = clustering.clusters.iter().flatten().cloned().collect();
+    members.sort_unstable();
+    assert_eq!(members, (0..mol_list.len()).collect::<Vec<usize>>());
+    for (c, centroid) in clustering.centroids().iter().enumerate() {
+        assert_eq!(clustering.assignments[*centroid], c);
+    }
+    // identical fingerprints always end up in one cluster
+    let clustering = butina_cluster(&[fps[0].clone(), fps[0].clone()], 0.0, false);
+    assert_eq!(clustering.num_clusters(), 1);
+
+    assert_eq!(
+        butina_cluster(&fps, 0.7, true),
+        butina_cluster(&fps, 0.7, false)
+    );
+}
+
+// ---- diversity picking tests ----
+
+#[test]
+fn maxmin_picking() {
+    let options = r#"{"radius":2,"nBits":1024}"#;
+    let mol_list: Vec<Molecule> = read_smifile_unwrap("data/ringtest.smi");
+    let fps = FingerprintKind::Morgan.compute_all(&mol_list, options);
+    let picks = maxmin_pick(&fps, 5);
+    assert_eq!(picks.len(), 5);
+    assert_eq!(picks[0], 0);
+    let mut unique = picks.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), 5);
+
+    let picks = MaxMinPicker::new(&fps).with_seed(Seed::Random(7)).pick(100);
+    assert_eq!(picks.len(), fps.len());
+    let picks = MaxMinPicker::new(&fps)
+        .with_seed(Seed::Index(fps.len()))
+        .pick(3);
+    assert!(picks.is_empty());
+
+    // an already owned structure is never picked first
+    let owned = vec![fps[2].clone()];
+    let picks = MaxMinPicker::new(&fps).with_owned(&owned).pick(3);
+    assert_ne!(picks[0], 2);
+    // nothing left which is distant enough
+    let picks = MaxMinPicker::new(&fps[..1])
+        .with_owned(&fps[..1])
+        .with_threshold(0.1)
+        .pick(3);
+    assert!(picks.is_empty());
+}
+
+// ---- substructure library tests ----
+
+#[test]
+fn substruct_library() {
+    let mol_list: Vec<Molecule> = read_smifile_unwrap("data/ringtest.smi");
+    let mut library = SubstructLibrary::new();
+    for mol in mol_list.iter() {
+        library.add_molecule(mol);
+    }
+    assert_eq!(library.len(), mol_list.len());
+
+    let query = Molecule::get_qmol("[CR]1[CR][CR][CR][CR]1", "").unwrap();
+    let expected: Vec<usize> = mol_list
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.get_substruct_match(&query, "") != "{}")
+        .map(|(i, _)| i)
+        .collect();
+    assert!(!expected.is_empty());
+    let hits = library.search(&query, None, false);
+    assert_eq!(hits, expected);
+    // screening never removes a true hit
+    let screened = library.screen(&query);
+    assert!(hits.iter().all(|h| screened.contains(h)));
+    assert_eq!(library.search(&query, None, true), expected);
+    assert_eq!(library.search(&query, Some(1), true), vec![expected[0]]);
+
+    let query = Molecule::get_qmol("[U]", "").unwrap();
+    assert!(library.search(&query, None, true).is_empty());
+}
+
 // ---- stale commented-out tests (removed, replaced above) ----
 
 // #[test]